@@ -0,0 +1,65 @@
+//! times the project-euler programs with label lookups, with linked
+//! targets and on the bytecode engine
+//!
+//! `cargo run --release --example euler_bench [problem numbers..]`
+
+use std::time::{Duration, Instant};
+
+use hex_vm::parse::Parser;
+
+fn main() {
+    let mut problems = std::env::args()
+        .skip(1)
+        .map(|n| n.parse().expect("expected a problem number"))
+        .collect::<Vec<u32>>();
+    if problems.is_empty() {
+        problems = (1..=9).collect();
+    }
+    // each speedup is over label lookups
+    println!(
+        "{:<8} {:>12} {:>12} {:>8} {:>12} {:>8}",
+        "problem", "lookup", "linked", "speedup", "bytecode", "speedup"
+    );
+    for n in problems {
+        let path = format!("project-euler/problem-{n}.asm");
+        let src = std::fs::read_to_string(&path).expect("unable to read source");
+        let lookup = time(&src, Engine::Lookup);
+        let linked = time(&src, Engine::Linked);
+        let bytecode = time(&src, Engine::Bytecode);
+        let speedup = |d: Duration| lookup.as_secs_f64() / d.as_secs_f64();
+        println!(
+            "{n:<8} {lookup:>12.2?} {linked:>12.2?} {:>7.2}x {bytecode:>12.2?} {:>7.2}x",
+            speedup(linked),
+            speedup(bytecode)
+        );
+    }
+}
+
+enum Engine {
+    Lookup,
+    Linked,
+    Bytecode,
+}
+
+fn time(src: &str, engine: Engine) -> Duration {
+    let program = Parser::new(src).program();
+    // building links the targets, put the unlinked ones back to time lookups
+    let seq = program.seq.clone();
+    let mut vm = program.build();
+    if let Engine::Lookup = engine {
+        vm.seq = seq;
+    }
+    let start = Instant::now();
+    match engine {
+        Engine::Lookup => {
+            while vm.seq.len() > vm.reg.ip as usize {
+                vm.step();
+            }
+        }
+        Engine::Linked => {
+            vm.run();
+        }
+        Engine::Bytecode => hex_vm::bytecode::run(&mut vm).expect("unable to lower"),
+    }
+    start.elapsed()
+}
@@ -0,0 +1,10 @@
+//! the language server, speaking json-rpc over stdin and stdout
+
+fn main() {
+    hex_vm::lsp::quiet_panics();
+    // stdout carries the protocol, so nothing else may print to it
+    let stdin = std::io::stdin();
+    let clean = hex_vm::lsp::serve(stdin.lock(), std::io::stdout().lock())
+        .expect("unable to talk to the client");
+    std::process::exit(!clean as i32);
+}
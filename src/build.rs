@@ -0,0 +1,177 @@
+//! assembled programs, and configuring a vm to run one
+//!
+//! [`Parser::program`](crate::parse::Parser::program) assembles source into
+//! a [`Program`], which a [`HexVmBuilder`] turns into a vm ready to run.
+
+use std::io::Write;
+
+use ahash::AHashMap;
+use string_interner::{DefaultStringInterner, DefaultSymbol};
+
+use crate::{
+    device::{Device, Output},
+    interrupt::Handler,
+    trace::{NoTracer, Tracer},
+    HexSize, HexVm, Register, RegisterSet, Sequence, Static, MEM_SIZE,
+};
+
+#[cfg(test)]
+mod test;
+
+/// everything the assembler produces, independent of any vm
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Program {
+    pub si: DefaultStringInterner,
+    pub labels: AHashMap<DefaultSymbol, HexSize>,
+    pub statics: Vec<Static>,
+    pub seq: Vec<Sequence>,
+    /// the zero based source line of each instruction
+    pub lines: Vec<u32>,
+    /// the label of each vector table entry, by interrupt number
+    pub vectors: Vec<Option<DefaultSymbol>>,
+    /// the instruction index execution starts at
+    pub entry: HexSize,
+}
+
+impl Program {
+    /// a vm with the default configuration
+    pub fn build(self) -> HexVm {
+        HexVmBuilder::new(self).build()
+    }
+}
+
+/// configures a vm for a program before it runs
+pub struct HexVmBuilder<T = NoTracer> {
+    program: Program,
+    words: usize,
+    entry: Option<HexSize>,
+    registers: Vec<(Register, HexSize)>,
+    output: Output,
+    devices: Vec<(HexSize, HexSize, Box<dyn Device>)>,
+    handlers: Vec<(HexSize, Handler)>,
+    fuel: Option<u64>,
+    tracer: T,
+}
+
+impl HexVmBuilder {
+    pub fn new(program: Program) -> Self {
+        Self {
+            program,
+            words: MEM_SIZE,
+            entry: None,
+            registers: Vec::new(),
+            output: Output::default(),
+            devices: Vec::new(),
+            handlers: Vec::new(),
+            fuel: None,
+            tracer: NoTracer,
+        }
+    }
+}
+
+impl<T: Tracer> HexVmBuilder<T> {
+    /// the memory size in words, `sp` and `bp` start at the top
+    pub fn memory(mut self, words: usize) -> Self {
+        self.words = words;
+        self
+    }
+
+    /// starts at instruction `index` rather than the program's entry
+    pub fn entry(mut self, index: HexSize) -> Self {
+        self.entry = Some(index);
+        self
+    }
+
+    /// starts `reg` at `word`, over the entry and the top of memory
+    pub fn register(mut self, reg: Register, word: HexSize) -> Self {
+        self.registers.push((reg, word));
+        self
+    }
+
+    /// sends `print` to `out` rather than stdout
+    pub fn output(mut self, out: impl Write + 'static) -> Self {
+        self.output = Output::new(out);
+        self
+    }
+
+    /// maps `len` words from `start` to the device, see [`HexVm::map`]
+    pub fn device(mut self, start: HexSize, len: HexSize, device: impl Device + 'static) -> Self {
+        self.devices.push((start, len, Box::new(device)));
+        self
+    }
+
+    /// extends the instruction set with a host handler for `int n`, these
+    /// are the only extensions and, like any `int`, only [`HexVm::run`]
+    /// runs them. [`bytecode::run`](crate::bytecode::run) refuses them
+    pub fn handle(
+        mut self,
+        n: HexSize,
+        handler: impl FnMut(&mut RegisterSet, &[HexSize]) + 'static,
+    ) -> Self {
+        self.handlers.push((n, Box::new(handler)));
+        self
+    }
+
+    /// stops [`HexVm::run`] once `cycles` instructions have run,
+    /// [`bytecode::run`](crate::bytecode::run) refuses a fuel limit
+    pub fn fuel(mut self, cycles: u64) -> Self {
+        self.fuel = Some(cycles);
+        self
+    }
+
+    pub fn tracer<U: Tracer>(self, tracer: U) -> HexVmBuilder<U> {
+        HexVmBuilder {
+            program: self.program,
+            words: self.words,
+            entry: self.entry,
+            registers: self.registers,
+            output: self.output,
+            devices: self.devices,
+            handlers: self.handlers,
+            fuel: self.fuel,
+            tracer,
+        }
+    }
+
+    pub fn build(self) -> HexVm<T> {
+        let Program {
+            si,
+            labels,
+            statics,
+            seq,
+            lines,
+            vectors,
+            entry,
+        } = self.program;
+        let mut vm = HexVm {
+            si,
+            statics,
+            lines,
+            vectors,
+            output: self.output,
+            fuel: self.fuel,
+            entry: self.entry.unwrap_or(entry),
+            ..HexVm::with_memory(seq, labels, self.words)
+        };
+        if vm.static_end() as usize > self.words {
+            panic!(
+                "a static area of {} words does not fit in {} words of memory",
+                vm.static_end(),
+                self.words
+            )
+        }
+        vm.load_statics();
+        vm.link();
+        vm.reg.ip = vm.entry;
+        for (reg, word) in self.registers {
+            *vm.reg_mut(reg) = word;
+        }
+        for (start, len, device) in self.devices {
+            vm.map(start, len, device);
+        }
+        for (n, handler) in self.handlers {
+            vm.handle(n, handler);
+        }
+        vm.with_tracer(self.tracer)
+    }
+}
@@ -0,0 +1,72 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use super::HexVmBuilder;
+use crate::{
+    device::CycleCounter, parse::Parser, profile::Profiler, watch::Stop, Register, RegisterSet,
+};
+
+/// a writer the test can still read after handing it to the vm
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn configured() {
+    let out = Shared::default();
+    let program = Parser::new(
+        r#"
+            mov ax, 1
+        main:
+            lea si, cx, "hi"
+            print si, 2
+            int 7
+            mov dx, [0x20]
+            push bx
+        "#,
+    )
+    .program();
+    let mut vm = HexVmBuilder::new(program)
+        .memory(64)
+        .entry(1)
+        .register(Register::Bx, 5)
+        .output(out.clone())
+        .device(0x20, 1, CycleCounter::default())
+        .handle(7, |reg: &mut RegisterSet, _: &[u64]| reg.di = 9)
+        .build();
+    assert_eq!((vm.mem.len(), vm.reg.sp, vm.reg.ip), (64, 64, 1));
+    assert_eq!(vm.run(), Stop::End);
+    assert_eq!(String::from_utf8(out.0.take()).unwrap(), "hi");
+    assert_eq!((vm.reg.ax, vm.reg.di, vm.reg.dx, vm.mem[63]), (0, 9, 3, 5));
+}
+
+#[test]
+fn fuel() {
+    let program = Parser::new("top:\ninc ax\njmp top").program();
+    let mut vm = HexVmBuilder::new(program)
+        .fuel(11)
+        .tracer(Profiler::new(2))
+        .build();
+    assert_eq!(vm.run(), Stop::Fuel);
+    assert_eq!((vm.cycles, vm.reg.ax), (11, 6));
+    assert_eq!(vm.tracer.counts, [6, 5]);
+    vm.fuel = Some(13);
+    assert_eq!(vm.run(), Stop::Fuel);
+    assert_eq!(vm.reg.ax, 7);
+}
+
+#[test]
+#[should_panic = "a static area of 2 words does not fit in 1 words of memory"]
+fn statics_too_big() {
+    HexVmBuilder::new(Parser::new(r#"str "hello, world""#).program())
+        .memory(1)
+        .build();
+}
@@ -0,0 +1,475 @@
+//! a second execution engine running a dense encoding of [`Sequence`]
+//!
+//! every instruction lowers to exactly one 64 bit word, so instruction
+//! indices and therefore jump targets stay the same:
+//!
+//! ```text
+//! 0       8      12      16                      40                      64
+//! | opcode | mode a | mode b |       operand a       |       operand b       |
+//! ```
+//!
+//! operands that do not fit in 24 bits are placed in a constant pool, and
+//! labels read or written at runtime get a slot of their own. the engine
+//! calls neither the vm's [`Tracer`] nor its history, it exists to run long
+//! programs fast and must otherwise behave exactly like [`HexVm::run`].
+//! what it cannot do is refused with [`Unsupported`] before anything runs.
+
+use std::{fmt, io::Write};
+
+use string_interner::{DefaultStringInterner, DefaultSymbol};
+
+use crate::{
+    device::Output, mem_str, undefined_label, Address, FlagSet, HexSize, HexVm, JmpKind, Op,
+    Register, Sequence, Value,
+};
+
+use crate::trace::Tracer;
+
+#[cfg(test)]
+mod test;
+
+const FIELD_BITS: u32 = 24;
+const FIELD_MAX: HexSize = (1 << FIELD_BITS) - 1;
+
+mod mode {
+    pub const REG: u8 = 0;
+    pub const REG_DEREF: u8 = 1;
+    pub const MEM: u8 = 2;
+    pub const MEM_CONST: u8 = 3;
+    pub const IMM: u8 = 4;
+    pub const CONST: u8 = 5;
+    pub const LABEL: u8 = 6;
+}
+
+mod op {
+    pub const MOV: u8 = 0;
+    pub const CMP: u8 = 1;
+    pub const JMP: u8 = 2;
+    pub const JE: u8 = 3;
+    pub const JNE: u8 = 4;
+    pub const JL: u8 = 5;
+    pub const JLE: u8 = 6;
+    pub const JG: u8 = 7;
+    pub const JGE: u8 = 8;
+    pub const CALL: u8 = 9;
+    pub const RET: u8 = 10;
+    pub const PUSH: u8 = 11;
+    pub const POP: u8 = 12;
+    pub const ADD: u8 = 13;
+    pub const SUB: u8 = 14;
+    pub const INC: u8 = 15;
+    pub const DEC: u8 = 16;
+    pub const MUL: u8 = 17;
+    pub const DIV: u8 = 18;
+    pub const MOD: u8 = 19;
+    pub const STR: u8 = 20;
+    pub const LEA: u8 = 21;
+    pub const PRINT: u8 = 22;
+    pub const EXIT: u8 = 23;
+}
+
+/// the operand part of an instruction word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Operand {
+    mode: u8,
+    field: HexSize,
+}
+
+impl Operand {
+    const NONE: Self = Self { mode: 0, field: 0 };
+
+    fn pack(self) -> HexSize {
+        (self.mode as HexSize) << FIELD_BITS | self.field
+    }
+
+    fn unpack(word: HexSize) -> Self {
+        Self {
+            mode: (word >> FIELD_BITS) as u8,
+            field: word & FIELD_MAX,
+        }
+    }
+}
+
+/// what the vm uses that the engine cannot run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unsupported {
+    Devices,
+    Protection,
+    Fuel,
+    Watchpoints,
+    Interrupts,
+    History,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self {
+            Unsupported::Devices => "devices",
+            Unsupported::Protection => "memory protection",
+            Unsupported::Fuel => "a fuel limit",
+            Unsupported::Watchpoints => "breakpoints or watches",
+            Unsupported::Interrupts => "interrupts",
+            Unsupported::History => "recording history",
+        };
+        write!(f, "the bytecode engine does not support {what}")
+    }
+}
+
+/// a lowered sequence, not to be confused with an assembled
+/// [`Program`](crate::build::Program)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Code {
+    pub code: Vec<u64>,
+    pub consts: Vec<HexSize>,
+    /// the label behind each label slot
+    pub labels: Vec<DefaultSymbol>,
+}
+
+impl Code {
+    /// links and lowers the vm's sequence
+    pub fn lower<T: Tracer>(vm: &mut HexVm<T>) -> Result<Self, Unsupported> {
+        use Sequence::{Int, Iret};
+        if vm.seq.iter().any(|seq| matches!(seq, Int(_) | Iret)) {
+            return Err(Unsupported::Interrupts);
+        }
+        vm.link();
+        let mut lowered = Self::default();
+        for (i, seq) in vm.seq.iter().enumerate() {
+            let word = lowered.instruction(vm, seq);
+            debug_assert_eq!(lowered.code.len(), i);
+            lowered.code.push(word);
+        }
+        Ok(lowered)
+    }
+
+    fn instruction<T: Tracer>(&mut self, vm: &HexVm<T>, seq: &Sequence) -> u64 {
+        use Sequence::*;
+        let (op, a, b) = match *seq {
+            Mov(add, value) => (op::MOV, self.address(add), self.value(value)),
+            Cmp(a, b) => (op::CMP, self.value(a), self.value(b)),
+            Jmp(value) => (op::JMP, self.value(value), Operand::NONE),
+            Je(value) => (op::JE, self.value(value), Operand::NONE),
+            Jne(value) => (op::JNE, self.value(value), Operand::NONE),
+            Jl(value) => (op::JL, self.value(value), Operand::NONE),
+            Jle(value) => (op::JLE, self.value(value), Operand::NONE),
+            Jg(value) => (op::JG, self.value(value), Operand::NONE),
+            Jge(value) => (op::JGE, self.value(value), Operand::NONE),
+            Call(value) => (op::CALL, self.value(value), Operand::NONE),
+            Ret => (op::RET, Operand::NONE, Operand::NONE),
+            Push(value) => (op::PUSH, self.value(value), Operand::NONE),
+            Pop(add) => (op::POP, self.address(add), Operand::NONE),
+            Add(add, value) => (op::ADD, self.address(add), self.value(value)),
+            Sub(add, value) => (op::SUB, self.address(add), self.value(value)),
+            Inc(add) => (op::INC, self.address(add), Operand::NONE),
+            Dec(add) => (op::DEC, self.address(add), Operand::NONE),
+            Mul(value) => (op::MUL, self.value(value), Operand::NONE),
+            Div(value) => (op::DIV, self.value(value), Operand::NONE),
+            Mod(value) => (op::MOD, self.value(value), Operand::NONE),
+            Str(i) | Sparse(i) => {
+                let st = vm.statics[i];
+                (op::STR, self.imm(st.addr), self.imm(st.len))
+            }
+            // the length destination and the static string itself spill
+            // into the pool: `[addr, len, packed length operand]`
+            Lea(add, len, i) => {
+                let st = vm.statics[i];
+                let len = self.address(len).pack();
+                let at = self.consts.len() as HexSize;
+                self.consts.extend([st.addr, st.len, len]);
+                (op::LEA, self.address(add), self.imm(at))
+            }
+            Print(add, len) => (op::PRINT, self.address(add), self.imm(len)),
+            Hlt => (op::EXIT, self.imm(0), Operand::NONE),
+            Exit(value) => (op::EXIT, self.value(value), Operand::NONE),
+            Int(_) | Iret => unreachable!("interrupts are refused before lowering"),
+        };
+        op as u64
+            | (a.mode as u64) << 8
+            | (b.mode as u64) << 12
+            | a.field << 16
+            | b.field << (16 + FIELD_BITS)
+    }
+
+    fn imm(&mut self, hx: HexSize) -> Operand {
+        if hx <= FIELD_MAX {
+            Operand {
+                mode: mode::IMM,
+                field: hx,
+            }
+        } else {
+            self.consts.push(hx);
+            Operand {
+                mode: mode::CONST,
+                field: self.consts.len() as HexSize - 1,
+            }
+        }
+    }
+
+    fn address(&mut self, add: Address) -> Operand {
+        match add {
+            Address::Register(r, deref) => Operand {
+                mode: if deref { mode::REG_DEREF } else { mode::REG },
+                field: r as HexSize,
+            },
+            Address::Stack(add) => match self.imm(add) {
+                Operand {
+                    mode: mode::CONST,
+                    field,
+                } => Operand {
+                    mode: mode::MEM_CONST,
+                    field,
+                },
+                Operand { field, .. } => Operand {
+                    mode: mode::MEM,
+                    field,
+                },
+            },
+            Address::Ident(sym) => {
+                let slot = match self.labels.iter().position(|&s| s == sym) {
+                    Some(slot) => slot,
+                    None => {
+                        self.labels.push(sym);
+                        self.labels.len() - 1
+                    }
+                };
+                Operand {
+                    mode: mode::LABEL,
+                    field: slot as HexSize,
+                }
+            }
+        }
+    }
+
+    fn value(&mut self, value: Value) -> Operand {
+        match value {
+            Value::Address(add) => self.address(add),
+            Value::Hex(hx) => self.imm(hx),
+            Value::IHex(ih) => self.imm(ih as HexSize),
+        }
+    }
+}
+
+/// lowers the vm's sequence and runs it to completion on the vm's state
+pub fn run<T: Tracer>(vm: &mut HexVm<T>) -> Result<(), Unsupported> {
+    if !vm.devices.is_empty() {
+        return Err(Unsupported::Devices);
+    }
+    if vm.protection.is_some() {
+        return Err(Unsupported::Protection);
+    }
+    if vm.fuel.is_some() {
+        return Err(Unsupported::Fuel);
+    }
+    if !vm.watch.is_empty() {
+        return Err(Unsupported::Watchpoints);
+    }
+    // nothing it runs could be stepped back
+    if vm.history.is_some() {
+        return Err(Unsupported::History);
+    }
+    let lowered = Code::lower(vm)?;
+    let static_end = vm.static_end();
+    let r = &vm.reg;
+    let mut m = Machine {
+        r: [r.ax, r.bx, r.cx, r.dx, r.si, r.di, r.sp, r.bp, r.ip],
+        flg: vm.flg,
+        mem: &mut vm.mem,
+        output: &mut vm.output,
+        consts: &lowered.consts,
+        labels: lowered
+            .labels
+            .iter()
+            .map(|sym| vm.labels.get(sym).copied())
+            .collect(),
+        names: &lowered.labels,
+        si: &vm.si,
+        static_end,
+        cycles: vm.cycles,
+        exit: vm.exit,
+    };
+    m.run(&lowered.code);
+    let [ax, bx, cx, dx, si, di, sp, bp, ip] = m.r;
+    let (flg, cycles, exit, labels) = (m.flg, m.cycles, m.exit, m.labels);
+    (vm.flg, vm.cycles, vm.exit) = (flg, cycles, exit);
+    for (&sym, label) in lowered.labels.iter().zip(labels) {
+        if let Some(label) = label {
+            vm.labels.insert(sym, label);
+        }
+    }
+    vm.reg = crate::RegisterSet {
+        ax,
+        bx,
+        cx,
+        dx,
+        si,
+        di,
+        sp,
+        bp,
+        ip,
+    };
+    Ok(())
+}
+
+const IP: usize = Register::Ip as usize;
+const SP: usize = Register::Sp as usize;
+const AX: usize = Register::Ax as usize;
+
+struct Machine<'a> {
+    r: [HexSize; 9],
+    flg: FlagSet,
+    mem: &'a mut [HexSize],
+    output: &'a mut Output,
+    consts: &'a [HexSize],
+    /// the value of each label slot, none if the label is undefined
+    labels: Vec<Option<HexSize>>,
+    names: &'a [DefaultSymbol],
+    si: &'a DefaultStringInterner,
+    static_end: HexSize,
+    cycles: u64,
+    exit: Option<HexSize>,
+}
+
+impl Machine<'_> {
+    fn run(&mut self, code: &[u64]) {
+        while let Some(&word) = code.get(self.r[IP] as usize) {
+            let old = self.r[IP];
+            let a = Operand {
+                mode: (word >> 8) as u8 & 0xF,
+                field: word >> 16 & FIELD_MAX,
+            };
+            let b = Operand {
+                mode: (word >> 12) as u8 & 0xF,
+                field: word >> (16 + FIELD_BITS),
+            };
+            match word as u8 {
+                op::MOV => {
+                    let v = self.read(b);
+                    self.write(a, v);
+                }
+                op::CMP => {
+                    let (a, b) = (self.read(a), self.read(b));
+                    self.flg.do_cmp(a, b);
+                }
+                op::JMP => self.jump(a, JmpKind::Jmp),
+                op::JE => self.jump(a, JmpKind::Je),
+                op::JNE => self.jump(a, JmpKind::Jne),
+                op::JL => self.jump(a, JmpKind::Jl),
+                op::JLE => self.jump(a, JmpKind::Jle),
+                op::JG => self.jump(a, JmpKind::Jg),
+                op::JGE => self.jump(a, JmpKind::Jge),
+                op::CALL => {
+                    self.push(old + 1);
+                    self.r[IP] = self.read(a);
+                }
+                op::RET => self.r[IP] = self.pop(),
+                op::PUSH => {
+                    let v = self.read(a);
+                    self.push(v);
+                }
+                op::POP => {
+                    let v = self.pop();
+                    self.write(a, v);
+                }
+                op::ADD => self.apply(a, self.read(b), Op::Add),
+                op::SUB => self.apply(a, self.read(b), Op::Sub),
+                op::INC => self.apply(a, 1, Op::Add),
+                op::DEC => self.apply(a, 1, Op::Sub),
+                op::MUL => self.r[AX] = self.flg.math(self.r[AX], self.read(a), Op::Mul),
+                op::DIV => self.r[AX] = self.flg.math(self.r[AX], self.read(a), Op::Div),
+                op::MOD => self.r[AX] = self.flg.math(self.r[AX], self.read(a), Op::Mod),
+                op::STR => {
+                    self.push(self.read(a));
+                    self.push(self.read(b));
+                }
+                op::LEA => {
+                    let at = self.read(b) as usize;
+                    let [addr, len, len_dst] = self.consts[at..at + 3] else {
+                        unreachable!()
+                    };
+                    self.write(a, addr);
+                    self.write(Operand::unpack(len_dst), len);
+                }
+                op::PRINT => {
+                    let s = mem_str(self.mem, self.read(a), self.read(b));
+                    write!(self.output, "{s}").expect("unable to write output");
+                }
+                op::EXIT => {
+                    self.exit = Some(self.read(a));
+                    self.r[IP] = code.len() as HexSize;
+                }
+                op => unreachable!("invalid opcode: {op}"),
+            }
+            self.r[IP] += (old == self.r[IP]) as HexSize;
+            self.cycles += 1;
+        }
+    }
+
+    #[inline(always)]
+    fn read(&self, o: Operand) -> HexSize {
+        let f = o.field as usize;
+        match o.mode {
+            mode::REG => self.r[f],
+            mode::REG_DEREF => self.mem[self.r[f] as usize],
+            mode::MEM => self.mem[f],
+            mode::MEM_CONST => self.mem[self.consts[f] as usize],
+            mode::IMM => o.field,
+            mode::CONST => self.consts[f],
+            mode::LABEL => match self.labels[f] {
+                Some(label) => label,
+                None => undefined_label(self.si, self.names[f]),
+            },
+            mode => unreachable!("invalid mode: {mode}"),
+        }
+    }
+
+    #[inline(always)]
+    fn write(&mut self, o: Operand, word: HexSize) {
+        let f = o.field as usize;
+        match o.mode {
+            mode::REG => self.r[f] = word,
+            mode::REG_DEREF => self.store(self.r[f], word),
+            mode::MEM => self.store(o.field, word),
+            mode::MEM_CONST => self.store(self.consts[f], word),
+            mode::LABEL => match &mut self.labels[f] {
+                Some(label) => *label = word,
+                None => undefined_label(self.si, self.names[f]),
+            },
+            mode => unreachable!("cannot write to mode: {mode}"),
+        }
+    }
+
+    fn store(&mut self, add: HexSize, word: HexSize) {
+        if add < self.static_end {
+            panic!("write to read-only static memory at {add}")
+        }
+        self.mem[add as usize] = word;
+    }
+
+    fn apply(&mut self, o: Operand, b: HexSize, op: Op) {
+        let v = self.flg.math(self.read(o), b, op);
+        self.write(o, v);
+    }
+
+    fn jump(&mut self, o: Operand, jmp: JmpKind) {
+        if self.flg.holds(jmp) {
+            self.r[IP] = self.read(o);
+        }
+    }
+
+    fn push(&mut self, word: HexSize) {
+        if self.r[SP] <= self.static_end {
+            panic!("used entire available memory; underflow.")
+        }
+        self.r[SP] -= 1;
+        self.mem[self.r[SP] as usize] = word;
+    }
+
+    fn pop(&mut self) -> HexSize {
+        if self.r[SP] as usize >= self.mem.len() {
+            panic!("used entire available memory; overflow.")
+        }
+        let val = self.mem[self.r[SP] as usize];
+        self.r[SP] += 1;
+        val
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    cell::RefCell,
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use super::Unsupported;
+use crate::{
+    device::{CycleCounter, Output},
+    parse::Parser,
+    Address, HexVm, Register, Sequence, Value,
+};
+
+/// a writer the test can still read after handing it to the vm
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// runs both engines on copies of the same vm and compares the final state
+/// and everything printed
+fn check_vm(make: impl Fn() -> HexVm) {
+    let (mut tree, mut bytecode) = (make(), make());
+    let printed = [Shared::default(), Shared::default()];
+    tree.output = Output::new(printed[0].clone());
+    bytecode.output = Output::new(printed[1].clone());
+    tree.run();
+    super::run(&mut bytecode).unwrap();
+    assert_eq!(printed[0].0, printed[1].0);
+    assert_eq!(tree.reg, bytecode.reg);
+    assert_eq!(tree.flg, bytecode.flg);
+    assert_eq!(tree.cycles, bytecode.cycles);
+    assert_eq!(tree.exit, bytecode.exit);
+    assert_eq!(tree.labels, bytecode.labels);
+    assert!(tree.mem == bytecode.mem, "memory differs");
+}
+
+fn check(src: &str) {
+    check_vm(|| Parser::new(src).parse());
+}
+
+#[test]
+fn euler() {
+    for src in [
+        include_str!("../../project-euler/problem-1.asm"),
+        include_str!("../../project-euler/problem-2.asm"),
+        include_str!("../../project-euler/problem-3.asm"),
+        include_str!("../../project-euler/problem-4.asm"),
+        include_str!("../../project-euler/problem-6.asm"),
+        include_str!("../../project-euler/problem-7.asm"),
+        include_str!("../../project-euler/problem-8.asm"),
+        include_str!("../../project-euler/problem-9.asm"),
+    ] {
+        check(src);
+    }
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore = "too slow without optimizations")]
+fn euler_slow() {
+    check(include_str!("../../project-euler/problem-5.asm"));
+}
+
+#[test]
+fn operands() {
+    check(
+        r#"
+                lea si, [40000], "a static string"
+                print si, 15
+                str "a static string"
+                sparse "sparse"
+                mov [50000], 600851475143
+                mov bx, [50000]
+                mov bp, [si]
+                add bp, -1
+                mov di, 30000
+                mov [di], sp
+                sub [di], 7
+                cmp [di], bx
+                jl +2
+                inc ax
+                push ip
+                call f
+                pop cx
+                jmp +2
+            f:
+                ret
+            "#,
+    )
+}
+
+const LABELS: &str = "
+        mov ax, 0
+        jmp target
+    target:
+        inc ax
+        inc ax
+        inc bx
+";
+
+#[test]
+fn labels() {
+    // the assembler has no syntax for label operands outside of jumps
+    check_vm(|| {
+        let mut program = Parser::new(LABELS).program();
+        let target = program.si.get("target").unwrap();
+        program.seq[0] = Sequence::Mov(Address::Ident(target), Value::Hex(4));
+        program.build()
+    });
+
+    let undefined = |engine: fn(&mut HexVm)| {
+        let mut program = Parser::new(LABELS).program();
+        let missing = program.si.get_or_intern("missing");
+        let cx = Address::Register(Register::Cx, false);
+        program.seq[0] = Sequence::Mov(cx, Value::Address(Address::Ident(missing)));
+        let mut vm = program.build();
+        let err = panic::catch_unwind(AssertUnwindSafe(|| engine(&mut vm))).unwrap_err();
+        crate::panic_message(err.as_ref()).to_owned()
+    };
+    let tree = undefined(|vm| {
+        vm.run();
+    });
+    assert_eq!(tree, "undefined label: missing");
+    assert_eq!(undefined(|vm| super::run(vm).unwrap()), tree);
+}
+
+#[test]
+fn unsupported() {
+    let refused = |vm: &mut HexVm| {
+        let before = (vm.reg, vm.seq.clone());
+        let err = super::run(vm).unwrap_err();
+        assert_eq!((vm.reg, vm.seq.clone()), before);
+        err
+    };
+    let mut vm = Parser::new("inc ax").parse();
+    vm.map(0x20, 1, CycleCounter::default());
+    assert_eq!(refused(&mut vm), Unsupported::Devices);
+    let mut vm = Parser::new("inc ax").parse();
+    vm.fuel = Some(1);
+    assert_eq!(refused(&mut vm), Unsupported::Fuel);
+    let mut vm = Parser::new("inc ax").parse();
+    vm.protect(crate::protect::DEFAULT_STACK);
+    assert_eq!(refused(&mut vm), Unsupported::Protection);
+    let mut vm = Parser::new("inc ax\nint 3").parse();
+    assert_eq!(refused(&mut vm), Unsupported::Interrupts);
+    let mut vm = Parser::new("inc ax").parse();
+    vm.record(16);
+    assert_eq!(refused(&mut vm), Unsupported::History);
+    assert_eq!(
+        Unsupported::Interrupts.to_string(),
+        "the bytecode engine does not support interrupts"
+    );
+}
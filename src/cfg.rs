@@ -0,0 +1,274 @@
+//! basic blocks and the control-flow graph between them
+
+use std::fmt::Write;
+
+use string_interner::DefaultSymbol;
+
+use crate::{trace::Tracer, Address, HexSize, HexVm, Sequence, Value};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// index of the first instruction
+    pub start: usize,
+    /// index past the last instruction
+    pub end: usize,
+    /// labels placed at `start`, sorted by name
+    pub labels: Vec<DefaultSymbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Fallthrough,
+    Branch,
+    Call,
+}
+
+/// where an edge leads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Node {
+    Block(usize),
+    /// past the last instruction or a halt, ending the program
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: Node,
+    pub kind: EdgeKind,
+}
+
+/// a block running into a label without jumping to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fallthrough {
+    pub block: usize,
+    pub label: DefaultSymbol,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+    pub edges: Vec<Edge>,
+    /// blocks not reachable from the entry
+    pub unreachable: Vec<usize>,
+    pub fallthroughs: Vec<Fallthrough>,
+}
+
+/// the instruction index a jump or call goes to, if it is known statically
+pub fn resolve<T: Tracer>(vm: &HexVm<T>, index: usize, value: Value) -> Option<HexSize> {
+    match value {
+        Value::Hex(hx) => Some(hx),
+        Value::IHex(diff) => Some((index as HexSize).wrapping_add_signed(diff)),
+        Value::Address(Address::Ident(sym)) => vm.labels.get(&sym).copied(),
+        Value::Address(_) => None,
+    }
+}
+
+impl Cfg {
+    pub fn new<T: Tracer>(vm: &HexVm<T>) -> Self {
+        let len = vm.seq.len();
+        let mut labels = vm
+            .labels
+            .iter()
+            .map(|(&sym, &i)| (i as usize, sym))
+            .collect::<Vec<_>>();
+        labels.sort_by_key(|&(i, sym)| (i, vm.si.resolve(sym)));
+
+        let targets = vm
+            .seq
+            .iter()
+            .enumerate()
+            .map(|(i, seq)| seq.target().and_then(|v| resolve(vm, i, v)))
+            .collect::<Vec<_>>();
+        let mut leader = vec![false; len + 1];
+        leader[0] = true;
+        leader[(vm.entry as usize).min(len)] = true;
+        for &(i, _) in &labels {
+            leader[i.min(len)] = true;
+        }
+        for (i, seq) in vm.seq.iter().enumerate() {
+            if let Some(t) = targets[i] {
+                leader[(t as usize).min(len)] = true;
+            }
+            if ends_block(seq) {
+                leader[i + 1] = true;
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut block_of = vec![0; len + 1];
+        for i in 0..len {
+            if leader[i] {
+                blocks.push(Block {
+                    start: i,
+                    end: i,
+                    labels: labels
+                        .iter()
+                        .filter(|&&(at, _)| at == i)
+                        .map(|&(_, sym)| sym)
+                        .collect(),
+                });
+            }
+            let block = blocks.len() - 1;
+            blocks[block].end = i + 1;
+            block_of[i] = block;
+        }
+        let node = |t: usize| match t < len {
+            true => Node::Block(block_of[t]),
+            false => Node::Exit,
+        };
+
+        let mut edges = Vec::new();
+        let mut fallthroughs = Vec::new();
+        for (b, block) in blocks.iter().enumerate() {
+            let last = block.end - 1;
+            let seq = &vm.seq[last];
+            if let Some(t) = targets[last] {
+                let kind = match seq {
+                    Sequence::Call(_) => EdgeKind::Call,
+                    _ => EdgeKind::Branch,
+                };
+                edges.push(Edge {
+                    from: b,
+                    to: node(t as usize),
+                    kind,
+                });
+            }
+            if matches!(seq, Sequence::Hlt | Sequence::Exit(_)) {
+                edges.push(Edge {
+                    from: b,
+                    to: Node::Exit,
+                    kind: EdgeKind::Branch,
+                });
+            }
+            if falls_through(seq) {
+                edges.push(Edge {
+                    from: b,
+                    to: node(block.end),
+                    kind: EdgeKind::Fallthrough,
+                });
+                // a call falls through to its return site on purpose
+                if !matches!(seq, Sequence::Call(_)) {
+                    fallthroughs.extend(labels.iter().filter(|&&(at, _)| at == block.end).map(
+                        |&(index, label)| Fallthrough {
+                            block: b,
+                            label,
+                            index,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let mut reached = vec![false; blocks.len()];
+        // vector table handlers are entered by `int`, not by an edge
+        let handlers = vm
+            .vectors
+            .iter()
+            .flatten()
+            .filter_map(|sym| vm.labels.get(sym));
+        let mut stack = std::iter::once(vm.entry)
+            .chain(handlers.copied())
+            .filter(|&at| (at as usize) < len)
+            .map(|at| block_of[at as usize])
+            .collect::<Vec<_>>();
+        while let Some(b) = stack.pop() {
+            if blocks.is_empty() || std::mem::replace(&mut reached[b], true) {
+                continue;
+            }
+            stack.extend(
+                edges
+                    .iter()
+                    .filter(|e| e.from == b)
+                    .filter_map(|e| match e.to {
+                        Node::Block(to) => Some(to),
+                        Node::Exit => None,
+                    }),
+            );
+        }
+        let unreachable = (0..blocks.len()).filter(|&b| !reached[b]).collect();
+
+        Self {
+            blocks,
+            edges,
+            unreachable,
+            fallthroughs,
+        }
+    }
+
+    /// renders the graph in graphviz dot
+    pub fn to_dot<T: Tracer>(&self, vm: &HexVm<T>) -> String {
+        let mut out = String::from("digraph cfg {\n    node [shape=box fontname=monospace];\n");
+        for (b, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for &sym in &block.labels {
+                let _ = write!(label, "{}:\\l", vm.si.resolve(sym).unwrap_or("?"));
+            }
+            for i in block.start..block.end {
+                let _ = write!(label, "{i}: {:?}\\l", vm.seq[i]);
+            }
+            let style = match self.unreachable.contains(&b) {
+                true => " style=dashed color=gray",
+                false => "",
+            };
+            let _ = writeln!(
+                out,
+                "    b{b} [label=\"{}\"{style}];",
+                label.replace('"', "\\\"")
+            );
+        }
+        if self.edges.iter().any(|e| e.to == Node::Exit) {
+            out.push_str("    exit [shape=doublecircle];\n");
+        }
+        for edge in &self.edges {
+            let to = match edge.to {
+                Node::Block(b) => format!("b{b}"),
+                Node::Exit => "exit".to_owned(),
+            };
+            let attrs = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Branch => " [label=\"branch\"]",
+                EdgeKind::Call => " [label=\"call\" style=dashed]",
+            };
+            let _ = writeln!(out, "    b{} -> {to}{attrs};", edge.from);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// describes unreachable code and fallthroughs into labels, one per line
+    pub fn report<T: Tracer>(&self, vm: &HexVm<T>) -> String {
+        let mut out = String::new();
+        for &b in &self.unreachable {
+            let block = &self.blocks[b];
+            let _ = writeln!(
+                out,
+                "unreachable: instructions {}..{}",
+                block.start, block.end
+            );
+        }
+        for fall in &self.fallthroughs {
+            let _ = writeln!(
+                out,
+                "fallthrough: instruction {} runs into `{}` at {}",
+                self.blocks[fall.block].end - 1,
+                vm.si.resolve(fall.label).unwrap_or("?"),
+                fall.index
+            );
+        }
+        out
+    }
+}
+
+fn ends_block(seq: &Sequence) -> bool {
+    seq.is_jump() || !falls_through(seq) || matches!(seq, Sequence::Call(_))
+}
+
+fn falls_through(seq: &Sequence) -> bool {
+    use Sequence::*;
+    !matches!(seq, Jmp(_) | Ret | Iret | Hlt | Exit(_))
+}
@@ -0,0 +1,77 @@
+use expect_test::expect;
+
+use super::Cfg;
+use crate::parse::Parser;
+
+#[test]
+fn dot() {
+    let vm = Parser::new(
+        "
+        start:
+            call f
+            cmp ax, 3
+            je end
+            jmp start
+            inc bx
+        f:
+            mov ax, 3
+            ret
+        end:
+        ",
+    )
+    .parse();
+    let cfg = Cfg::new(&vm);
+    expect![[r#"
+        digraph cfg {
+            node [shape=box fontname=monospace];
+            b0 [label="start:\l0: Call(Hex(5))\l"];
+            b1 [label="1: Cmp(Address(Register(Ax, false)), Hex(3))\l2: Je(Hex(7))\l"];
+            b2 [label="3: Jmp(Hex(0))\l"];
+            b3 [label="4: Inc(Register(Bx, false))\l" style=dashed color=gray];
+            b4 [label="f:\l5: Mov(Register(Ax, false), Hex(3))\l6: Ret\l"];
+            exit [shape=doublecircle];
+            b0 -> b4 [label="call" style=dashed];
+            b0 -> b1;
+            b1 -> exit [label="branch"];
+            b1 -> b2;
+            b2 -> b0 [label="branch"];
+            b3 -> b4;
+        }
+    "#]]
+    .assert_eq(&cfg.to_dot(&vm));
+    expect![[r#"
+        unreachable: instructions 4..5
+        fallthrough: instruction 4 runs into `f` at 5
+    "#]]
+    .assert_eq(&cfg.report(&vm));
+}
+
+#[test]
+fn euler_fallthrough() {
+    let vm = Parser::new(include_str!("../../project-euler/problem-9.asm")).parse();
+    expect![[r#"
+        fallthrough: instruction 0 runs into `loop_i` at 1
+        fallthrough: instruction 2 runs into `loop_j` at 3
+        fallthrough: instruction 24 runs into `loop_j_end` at 25
+        fallthrough: instruction 27 runs into `loop_i_end` at 28
+        fallthrough: instruction 30 runs into `end` at 31
+    "#]]
+    .assert_eq(&Cfg::new(&vm).report(&vm));
+}
+
+#[test]
+fn vectors() {
+    let vm = Parser::new(
+        "
+        .vector 3, handler
+        start:
+            int 3
+            hlt
+        handler:
+            inc ax
+            iret
+        ",
+    )
+    .parse();
+    assert_eq!(Cfg::new(&vm).report(&vm), "");
+}
@@ -0,0 +1,115 @@
+//! per line coverage of a run, as an annotated listing or lcov
+
+use std::fmt::Write;
+
+use crate::{trace::Tracer, FlagSet, HexSize, HexVm, JmpKind, RegisterSet, Sequence};
+
+#[cfg(test)]
+mod test;
+
+/// a tracer counting executions per instruction index, and for each
+/// conditional jump how often it was taken
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub hits: Vec<u64>,
+    pub taken: Vec<u64>,
+    pub not_taken: Vec<u64>,
+}
+
+impl Coverage {
+    pub fn new(len: usize) -> Self {
+        Self {
+            hits: vec![0; len],
+            taken: vec![0; len],
+            not_taken: vec![0; len],
+        }
+    }
+
+    /// the source with each instruction line prefixed by its count, `#####`
+    /// when it never ran, and each conditional jump suffixed by its branches
+    pub fn listing<T: Tracer>(&self, vm: &HexVm<T>, src: &str) -> String {
+        let mut at = vec![None; src.lines().count()];
+        for (i, &line) in vm.lines.iter().enumerate() {
+            if let Some(at) = at.get_mut(line as usize) {
+                *at = Some(i);
+            }
+        }
+        let mut out = String::new();
+        for (line, text) in src.lines().enumerate() {
+            let _ = match at[line] {
+                Some(i) if self.is_branch(vm, i) => writeln!(
+                    out,
+                    "{:>9} | {text}  [taken {}, not taken {}]",
+                    count(get(&self.hits, i)),
+                    get(&self.taken, i),
+                    get(&self.not_taken, i)
+                ),
+                Some(i) => writeln!(out, "{:>9} | {text}", count(get(&self.hits, i))),
+                None => writeln!(out, "{:>9} | {text}", ""),
+            };
+        }
+        out
+    }
+
+    /// an lcov tracefile record for the source at `path`
+    pub fn lcov<T: Tracer>(&self, vm: &HexVm<T>, path: &str) -> String {
+        let mut out = format!("TN:\nSF:{path}\n");
+        let (mut branches, mut branches_hit) = (0, 0);
+        for (i, &line) in vm.lines.iter().enumerate() {
+            if !self.is_branch(vm, i) {
+                continue;
+            }
+            for (n, count) in [get(&self.taken, i), get(&self.not_taken, i)]
+                .into_iter()
+                .enumerate()
+            {
+                let count = match get(&self.hits, i) {
+                    0 => "-".to_owned(),
+                    _ => count.to_string(),
+                };
+                let _ = writeln!(out, "BRDA:{},0,{n},{count}", line + 1);
+                branches += 1;
+                branches_hit += (count != "-" && count != "0") as usize;
+            }
+        }
+        let _ = writeln!(out, "BRF:{branches}\nBRH:{branches_hit}");
+        for (i, &line) in vm.lines.iter().enumerate() {
+            let _ = writeln!(out, "DA:{},{}", line + 1, get(&self.hits, i));
+        }
+        let hit = self.hits.iter().filter(|&&h| h > 0).count();
+        let _ = writeln!(out, "LF:{}\nLH:{hit}\nend_of_record", vm.lines.len());
+        out
+    }
+
+    fn is_branch<T: Tracer>(&self, vm: &HexVm<T>, i: usize) -> bool {
+        vm.seq[i].jmp_kind().is_some_and(|k| k != JmpKind::Jmp)
+    }
+}
+
+fn get(counts: &[u64], i: usize) -> u64 {
+    counts.get(i).copied().unwrap_or(0)
+}
+
+fn count(hits: u64) -> String {
+    match hits {
+        0 => "#####".to_owned(),
+        hits => hits.to_string(),
+    }
+}
+
+impl Tracer for Coverage {
+    fn before(&mut self, ip: HexSize, seq: &Sequence, _: &RegisterSet, flg: &FlagSet) {
+        let ip = ip as usize;
+        if ip >= self.hits.len() {
+            self.hits.resize(ip + 1, 0);
+            self.taken.resize(ip + 1, 0);
+            self.not_taken.resize(ip + 1, 0);
+        }
+        self.hits[ip] += 1;
+        match seq.jmp_kind() {
+            Some(JmpKind::Jmp) | None => (),
+            Some(kind) if flg.holds(kind) => self.taken[ip] += 1,
+            Some(_) => self.not_taken[ip] += 1,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use expect_test::expect;
+
+use super::Coverage;
+use crate::parse::Parser;
+
+#[test]
+fn listing_and_lcov() {
+    let src = "start:
+    mov cx, 2
+loop:
+    dec cx
+    cmp cx, 5
+    jg  never
+    cmp cx, 0
+    jne loop
+    jmp end
+never:
+    inc dx
+end:
+";
+    let vm = Parser::new(src).parse();
+    let len = vm.seq.len();
+    let mut vm = vm.with_tracer(Coverage::new(len));
+    vm.run();
+    // expect trims the shared indentation, not the padding
+    let listing = vm
+        .tracer
+        .listing(&vm, src)
+        .lines()
+        .map(|l| l.trim_start().to_owned() + "\n")
+        .collect::<String>();
+    let lcov = vm.tracer.lcov(&vm, "loop.asm");
+    expect![[r#"
+        | start:
+        1 |     mov cx, 2
+        | loop:
+        2 |     dec cx
+        2 |     cmp cx, 5
+        2 |     jg  never  [taken 0, not taken 2]
+        2 |     cmp cx, 0
+        2 |     jne loop  [taken 1, not taken 1]
+        1 |     jmp end
+        | never:
+        ##### |     inc dx
+        | end:
+    "#]]
+    .assert_eq(&listing);
+    expect![[r#"
+        TN:
+        SF:loop.asm
+        BRDA:6,0,0,0
+        BRDA:6,0,1,2
+        BRDA:8,0,0,1
+        BRDA:8,0,1,1
+        BRF:4
+        BRH:3
+        DA:2,1
+        DA:4,2
+        DA:5,2
+        DA:6,2
+        DA:7,2
+        DA:8,2
+        DA:9,1
+        DA:11,0
+        LF:8
+        LH:7
+        end_of_record
+    "#]]
+    .assert_eq(&lcov);
+}
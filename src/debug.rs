@@ -0,0 +1,154 @@
+//! a line based debugger over any reader and writer
+//!
+//! ```text
+//! break LOC [if EXPR]          stop before an instruction index or label
+//! delete N                     remove the Nth breakpoint
+//! watch TARGET [read|write|change]
+//! continue                     run until the next stop
+//! step                         run one instruction
+//! back                         undo one instruction
+//! print EXPR                   evaluate an expression
+//! quit
+//! ```
+//!
+//! the first letter of each command works too. see [`crate::expr`] for
+//! the expression syntax.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    expr::Expr,
+    trace::Tracer,
+    watch::{Access, Breakpoint, Stop, Target, Watch},
+    HexSize, HexVm,
+};
+
+#[cfg(test)]
+mod test;
+
+/// steps kept for `back`
+pub const HISTORY: usize = 1 << 20;
+
+pub fn repl<T: Tracer>(
+    vm: &mut HexVm<T>,
+    src: &str,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> io::Result<()> {
+    if vm.history.is_none() {
+        vm.record(HISTORY);
+    }
+    writeln!(out, "{}", location(vm, src))?;
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let reply = match cmd {
+            "" => continue,
+            "q" | "quit" => break,
+            "b" | "break" => add_break(vm, rest),
+            "d" | "delete" => match rest.parse::<usize>() {
+                Ok(n) if n < vm.watch.breakpoints.len() => {
+                    vm.watch.breakpoints.remove(n);
+                    Ok(format!("deleted breakpoint {n}"))
+                }
+                _ => Err(format!("no breakpoint `{rest}`")),
+            },
+            "w" | "watch" => add_watch(vm, rest),
+            "c" | "continue" => {
+                let stop = vm.run();
+                Ok(format!("{stop}\n{}", location(vm, src)))
+            }
+            "s" | "step" => match vm.seq.len() > vm.reg.ip as usize {
+                true => {
+                    let ip = vm.reg.ip;
+                    vm.step();
+                    match vm.take_fault(ip) {
+                        Some(fault) => Ok(format!("{}\n{}", Stop::Fault(fault), location(vm, src))),
+                        None => Ok(location(vm, src)),
+                    }
+                }
+                false => Err("the program has ended".to_owned()),
+            },
+            "back" => match vm.step_back() {
+                true => Ok(location(vm, src)),
+                false => Err("no history left".to_owned()),
+            },
+            "p" | "print" => Expr::parse(rest).map(|e| match e.eval(vm, 0) {
+                Some(v) => v.to_string(),
+                None => "undefined".to_owned(),
+            }),
+            _ => Err(format!("unknown command `{cmd}`")),
+        };
+        match reply {
+            Ok(reply) => writeln!(out, "{reply}")?,
+            Err(err) => writeln!(out, "error: {err}")?,
+        }
+    }
+    Ok(())
+}
+
+/// the next instruction and its source line
+fn location<T: Tracer>(vm: &HexVm<T>, src: &str) -> String {
+    let ip = vm.reg.ip as usize;
+    if ip >= vm.seq.len() {
+        return format!("{ip}: end");
+    }
+    let text = vm
+        .lines
+        .get(ip)
+        .and_then(|&line| src.lines().nth(line as usize))
+        .map_or_else(
+            || format!("{:?}", vm.seq[ip]),
+            |text| text.trim().to_owned(),
+        );
+    format!("{ip}: {text}")
+}
+
+fn add_break<T: Tracer>(vm: &mut HexVm<T>, args: &str) -> Result<String, String> {
+    let (loc, cond) = match args.split_once(" if ") {
+        Some((loc, cond)) => (loc.trim(), Some(Expr::parse(cond)?)),
+        None => (args, None),
+    };
+    let index = match loc.parse::<HexSize>() {
+        Ok(index) => index,
+        Err(_) => vm
+            .si
+            .get(loc)
+            .and_then(|sym| vm.labels.get(&sym).copied())
+            .ok_or_else(|| format!("no label `{loc}`"))?,
+    };
+    vm.watch.breakpoints.push(Breakpoint {
+        index,
+        cond,
+        hits: 0,
+    });
+    Ok(format!(
+        "breakpoint {} at instruction {index}",
+        vm.watch.breakpoints.len() - 1
+    ))
+}
+
+fn add_watch<T: Tracer>(vm: &mut HexVm<T>, args: &str) -> Result<String, String> {
+    let (target, access) = match args.rsplit_once(' ') {
+        Some((target, "read")) => (target, Access::Read),
+        Some((target, "write")) => (target, Access::Write),
+        Some((target, "change")) => (target, Access::Change),
+        _ => (args, Access::Change),
+    };
+    let target = match Expr::parse(target)? {
+        Expr::Reg(r) => Target::Reg(r),
+        Expr::Mem(add) => match add.eval(vm, 0) {
+            Some(add) => Target::Mem(add),
+            None => return Err("undefined address".to_owned()),
+        },
+        _ => {
+            return Err(format!(
+                "can only watch a register or `[addr]`, not `{target}`"
+            ))
+        }
+    };
+    vm.watch.watches.push(Watch { target, access });
+    Ok(format!("watching {target}"))
+}
@@ -0,0 +1,86 @@
+use expect_test::expect;
+
+use super::repl;
+use crate::parse::Parser;
+
+const SRC: &str = "start:
+    mov cx, 0
+loop:
+    inc cx
+    push cx
+    cmp cx, 5
+    jl loop
+    pop ax
+";
+
+#[test]
+fn session() {
+    let input = "\
+break loop if cx == 3
+watch [sp - 3]
+c
+p cx * 2
+c
+back
+s
+p [sp] + 1
+d 0
+delete 4
+w ax write
+c
+break nowhere
+print 1 +
+frob
+quit
+continue
+";
+    let mut vm = Parser::new(SRC).parse();
+    let mut out = Vec::new();
+    repl(&mut vm, SRC, input.as_bytes(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    expect![[r#"
+        0: mov cx, 0
+        breakpoint 0 at instruction 1
+        watching [61163]
+        [61163] changed at instruction 2 (line 5): 0 -> 3
+        3: cmp cx, 5
+        6
+        breakpoint at instruction 1
+        1: inc cx
+        4: jl loop
+        1: inc cx
+        4
+        deleted breakpoint 0
+        error: no breakpoint `4`
+        watching ax
+        ax written at instruction 5 (line 8): 0 -> 5
+        6: end
+        error: no label `nowhere`
+        error: unexpected end of expression
+        error: unknown command `frob`
+    "#]]
+    .assert_eq(&out);
+}
+
+#[test]
+fn protected_step() {
+    let src = "lea si, cx, \"hi\"\nmov [5000], 7\nmov ax, [5000]\nmov [si], 5\n";
+    let mut vm = Parser::new(src).parse();
+    vm.protect(crate::protect::DEFAULT_STACK);
+    let mut out = Vec::new();
+    let input = "s\ns\ns\ns\ns\np ax\np [0] == 5";
+    repl(&mut vm, src, input.as_bytes(), &mut out).unwrap();
+    expect![[r#"
+        0: lea si, cx, "hi"
+        1: mov [5000], 7
+        2: mov ax, [5000]
+        3: mov [si], 5
+        fault: write to 0 in static at instruction 3 (line 4)
+        3: mov [si], 5
+        fault: write to 0 in static at instruction 3 (line 4)
+        3: mov [si], 5
+        7
+        0
+    "#]]
+    .assert_eq(&String::from_utf8(out).unwrap());
+}
@@ -0,0 +1,213 @@
+//! memory-mapped devices
+//!
+//! a device owns a range of addresses. `[addr]` reads and writes inside
+//! the range call the device instead of touching [`HexVm::mem`], pushes and
+//! pops always go to memory. device effects are not recorded in the
+//! history or in snapshots.
+//!
+//! `print` does not go through a device, it writes to the vm's [`Output`].
+
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{trace::Tracer, HexSize, HexVm};
+
+#[cfg(test)]
+mod test;
+
+/// a handler for a mapped range, `offset` is relative to its start
+pub trait Device {
+    fn read(&mut self, offset: HexSize, cycles: u64) -> HexSize;
+    fn write(&mut self, offset: HexSize, word: HexSize, cycles: u64);
+}
+
+impl<D: Device + ?Sized> Device for Box<D> {
+    fn read(&mut self, offset: HexSize, cycles: u64) -> HexSize {
+        (**self).read(offset, cycles)
+    }
+
+    fn write(&mut self, offset: HexSize, word: HexSize, cycles: u64) {
+        (**self).write(offset, word, cycles)
+    }
+}
+
+struct Mapping {
+    start: HexSize,
+    end: HexSize,
+    // reads happen through `&HexVm`
+    device: RefCell<Box<dyn Device>>,
+}
+
+/// every mapped device, compared and printed by their ranges only
+#[derive(Default)]
+pub struct Devices {
+    mappings: Vec<Mapping>,
+}
+
+impl Devices {
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// the `start..end` of every mapped range
+    pub fn ranges(&self) -> impl Iterator<Item = (HexSize, HexSize)> + '_ {
+        self.mappings.iter().map(|m| (m.start, m.end))
+    }
+
+    fn find(&self, add: HexSize) -> Option<&Mapping> {
+        self.mappings
+            .iter()
+            .find(|m| (m.start..m.end).contains(&add))
+    }
+
+    pub(crate) fn read(&self, add: HexSize, cycles: u64) -> Option<HexSize> {
+        let m = self.find(add)?;
+        Some(m.device.borrow_mut().read(add - m.start, cycles))
+    }
+
+    pub(crate) fn write(&self, add: HexSize, word: HexSize, cycles: u64) -> bool {
+        match self.find(add) {
+            Some(m) => {
+                m.device.borrow_mut().write(add - m.start, word, cycles);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for Devices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.mappings.iter().map(|m| m.start..m.end))
+            .finish()
+    }
+}
+
+impl PartialEq for Devices {
+    fn eq(&self, other: &Self) -> bool {
+        self.ranges().eq(other.ranges())
+    }
+}
+
+impl Eq for Devices {}
+
+impl<T: Tracer> HexVm<T> {
+    /// maps `len` words from `start` to the device
+    pub fn map(&mut self, start: HexSize, len: HexSize, device: impl Device + 'static) {
+        let end = start + len;
+        if start < self.static_end() || end > self.mem.len() as HexSize {
+            panic!("device range {start}..{end} is outside writable memory")
+        }
+        if let Some((s, e)) = self.devices.ranges().find(|&(s, e)| start < e && s < end) {
+            panic!("device range {start}..{end} overlaps {s}..{e}")
+        }
+        self.devices.mappings.push(Mapping {
+            start,
+            end,
+            device: RefCell::new(Box::new(device)),
+        });
+    }
+}
+
+/// character i/o through a single word
+///
+/// writing emits the word as a character, reading takes the next input
+/// byte, or `HexSize::MAX` once the input is exhausted.
+pub struct Console<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: Read, W: Write> Console<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<R: Read, W: Write> Device for Console<R, W> {
+    fn read(&mut self, _: HexSize, _: u64) -> HexSize {
+        let mut byte = [0];
+        match self.input.read(&mut byte).expect("unable to read console") {
+            0 => HexSize::MAX,
+            _ => byte[0] as HexSize,
+        }
+    }
+
+    fn write(&mut self, _: HexSize, word: HexSize, _: u64) {
+        let c = char::from_u32(word as u32).unwrap_or(char::REPLACEMENT_CHARACTER);
+        write!(self.output, "{c}").expect("unable to write console");
+    }
+}
+
+/// reads the cycles executed since it was last written, writing sets the
+/// count it reads from then on
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CycleCounter {
+    base: u64,
+}
+
+impl Device for CycleCounter {
+    fn read(&mut self, _: HexSize, cycles: u64) -> HexSize {
+        cycles.wrapping_sub(self.base)
+    }
+
+    fn write(&mut self, _: HexSize, word: HexSize, cycles: u64) {
+        self.base = cycles.wrapping_sub(word);
+    }
+}
+
+/// where `print` writes, stdout unless replaced. compared and printed by
+/// whether it was replaced only
+#[derive(Default)]
+pub struct Output {
+    out: Option<Box<dyn Write>>,
+}
+
+impl Output {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self {
+            out: Some(Box::new(out)),
+        }
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        self.out.is_none()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.out {
+            Some(out) => out.write(buf),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.out {
+            Some(out) => out.flush(),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_stdout() {
+            true => write!(f, "Output(stdout)"),
+            false => write!(f, "Output(..)"),
+        }
+    }
+}
+
+impl PartialEq for Output {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_stdout() == other.is_stdout()
+    }
+}
+
+impl Eq for Output {}
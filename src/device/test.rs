@@ -0,0 +1,64 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use expect_test::expect;
+
+use super::{Console, CycleCounter};
+use crate::parse::Parser;
+
+/// a writer the test can still read after handing it to the vm
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn console_and_counter() {
+    let mut vm = Parser::new(
+        "
+            mov [0x1000], 'h'
+            mov [0x1000], 'i'
+            mov [0x1000], ' '
+            mov [0x1001], 0
+        echo:
+            mov ax, [0x1000]
+            cmp ax, -1
+            je end
+            sub ax, 32
+            mov [0x1000], ax
+            jmp echo
+        end:
+            mov bx, [0x1001]
+            ",
+    )
+    .parse();
+    let out = Shared::default();
+    vm.map(0x1000, 1, Console::new(&b"ok"[..], out.clone()));
+    vm.map(0x1001, 1, CycleCounter::default());
+    vm.run();
+    expect![[r#"
+            "hi OK" bx: 16 mem: [0, 0] devices: [4096..4097, 4097..4098]
+        "#]]
+    .assert_eq(&format!(
+        "{:?} bx: {} mem: {:?} devices: {:?}\n",
+        String::from_utf8_lossy(&out.0.borrow()),
+        vm.reg.bx,
+        &vm.mem[0x1000..0x1002],
+        vm.devices
+    ));
+}
+
+#[test]
+#[should_panic = "device range 4099..4100 overlaps 4096..4100"]
+fn overlap() {
+    let mut vm = Parser::new("").parse();
+    vm.map(0x1000, 4, CycleCounter::default());
+    vm.map(0x1003, 1, CycleCounter::default());
+}
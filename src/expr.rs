@@ -0,0 +1,324 @@
+//! expressions over vm state, for conditional breakpoints and printing
+//!
+//! ```text
+//! expr  = and ("||" and)*
+//! and   = cmp ("&&" cmp)*
+//! cmp   = sum (("==" | "!=" | "<" | "<=" | ">" | ">=") sum)?
+//! sum   = prod (("+" | "-") prod)*
+//! prod  = unary (("*" | "/" | "%") unary)*
+//! unary = ("!" | "-") unary | atom
+//! atom  = number | 'c' | register | flag | "hits" | "cycles"
+//!       | "[" expr "]" | "(" expr ")"
+//! ```
+//!
+//! every value is a wrapping [`HexSize`], conditions are true when non-zero
+//! and produce 1 or 0. flags are `sf`, `cf`, `zf` and `of`.
+
+use std::fmt;
+
+use crate::{trace::Tracer, HexSize, HexVm, Register};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Sf,
+    Cf,
+    Zf,
+    Of,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Num(HexSize),
+    Reg(Register),
+    Flag(Flag),
+    /// times the breakpoint being checked was reached, this one included
+    Hits,
+    Cycles,
+    Mem(Box<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Bin(Box<Expr>, BinOp, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let tokens = tokens(src)?;
+        let mut p = Parser { tokens, at: 0 };
+        let expr = p.or()?;
+        match p.peek() {
+            None => Ok(expr),
+            Some(tok) => Err(format!("unexpected `{tok}`")),
+        }
+    }
+
+    /// the value in the vm's current state, `None` when it divides by zero
+    /// or reads past memory
+    pub fn eval<T: Tracer>(&self, vm: &HexVm<T>, hits: u64) -> Option<HexSize> {
+        Some(match self {
+            Expr::Num(n) => *n,
+            Expr::Reg(r) => vm.reg.get(*r),
+            Expr::Flag(f) => {
+                let set = match f {
+                    Flag::Sf => vm.flg.sf,
+                    Flag::Cf => vm.flg.cf,
+                    Flag::Zf => vm.flg.zf,
+                    Flag::Of => vm.flg.of,
+                };
+                set as HexSize
+            }
+            Expr::Hits => hits,
+            Expr::Cycles => vm.cycles,
+            // read directly, so devices and watches are not involved
+            Expr::Mem(add) => *vm.mem.get(add.eval(vm, hits)? as usize)?,
+            Expr::Not(e) => (e.eval(vm, hits)? == 0) as HexSize,
+            Expr::Neg(e) => e.eval(vm, hits)?.wrapping_neg(),
+            Expr::Bin(a, BinOp::Or, b) => {
+                (a.eval(vm, hits)? != 0 || b.eval(vm, hits)? != 0) as HexSize
+            }
+            Expr::Bin(a, BinOp::And, b) => {
+                (a.eval(vm, hits)? != 0 && b.eval(vm, hits)? != 0) as HexSize
+            }
+            Expr::Bin(a, op, b) => {
+                let (a, b) = (a.eval(vm, hits)?, b.eval(vm, hits)?);
+                match op {
+                    BinOp::Eq => (a == b) as HexSize,
+                    BinOp::Ne => (a != b) as HexSize,
+                    BinOp::Lt => (a < b) as HexSize,
+                    BinOp::Le => (a <= b) as HexSize,
+                    BinOp::Gt => (a > b) as HexSize,
+                    BinOp::Ge => (a >= b) as HexSize,
+                    BinOp::Add => a.wrapping_add(b),
+                    BinOp::Sub => a.wrapping_sub(b),
+                    BinOp::Mul => a.wrapping_mul(b),
+                    BinOp::Div => a.checked_div(b)?,
+                    BinOp::Mod => a.checked_rem(b)?,
+                    BinOp::Or | BinOp::And => unreachable!(),
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Num(HexSize),
+    Ident(String),
+    /// an operator or bracket
+    Punct(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{n}"),
+            Token::Ident(s) => write!(f, "{s}"),
+            Token::Punct(p) => write!(f, "{p}"),
+        }
+    }
+}
+
+/// longer operators first, so `<=` is not read as `<`
+const PUNCT: [&str; 18] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", "[", "]",
+];
+
+fn tokens(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = src.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Num(number(&rest[..len])?));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_owned()));
+            len
+        } else if c == '\'' {
+            let mut chars = rest[1..].chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), Some('\'')) => {
+                    tokens.push(Token::Num(c as HexSize));
+                    c.len_utf8() + 2
+                }
+                _ => return Err("unterminated character".to_owned()),
+            }
+        } else {
+            let Some(p) = PUNCT.iter().find(|p| rest.starts_with(**p)) else {
+                return Err(format!("unexpected `{c}`"));
+            };
+            tokens.push(Token::Punct(p));
+            p.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+fn number(s: &str) -> Result<HexSize, String> {
+    let digits = s.replace('_', "");
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x") => (&digits[2..], 16),
+        Some("0b") => (&digits[2..], 2),
+        Some("0o") => (&digits[2..], 8),
+        _ => (&digits[..], 10),
+    };
+    HexSize::from_str_radix(digits, radix).map_err(|_| format!("invalid number `{s}`"))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Punct(p)) if *p == punct);
+        self.at += found as usize;
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        match self.eat(punct) {
+            true => Ok(()),
+            false => Err(match self.peek() {
+                Some(tok) => format!("expected `{punct}`, found `{tok}`"),
+                None => format!("expected `{punct}`"),
+            }),
+        }
+    }
+
+    /// a left associative chain of the given operators
+    fn chain(
+        &mut self,
+        ops: &[(&str, BinOp)],
+        next: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut expr = next(self)?;
+        'chain: loop {
+            for &(punct, op) in ops {
+                if self.eat(punct) {
+                    expr = Expr::Bin(Box::new(expr), op, Box::new(next(self)?));
+                    continue 'chain;
+                }
+            }
+            return Ok(expr);
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.chain(&[("||", BinOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.chain(&[("&&", BinOp::And)], Self::cmp)
+    }
+
+    fn cmp(&mut self) -> Result<Expr, String> {
+        let expr = self.sum()?;
+        let ops = [
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+            ("<=", BinOp::Le),
+            (">=", BinOp::Ge),
+            ("<", BinOp::Lt),
+            (">", BinOp::Gt),
+        ];
+        for (punct, op) in ops {
+            if self.eat(punct) {
+                return Ok(Expr::Bin(Box::new(expr), op, Box::new(self.sum()?)));
+            }
+        }
+        Ok(expr)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.chain(&[("+", BinOp::Add), ("-", BinOp::Sub)], Self::prod)
+    }
+
+    fn prod(&mut self) -> Result<Expr, String> {
+        let ops = [("*", BinOp::Mul), ("/", BinOp::Div), ("%", BinOp::Mod)];
+        self.chain(&ops, Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        if self.eat("(") {
+            let expr = self.or()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        if self.eat("[") {
+            let expr = self.or()?;
+            self.expect("]")?;
+            return Ok(Expr::Mem(Box::new(expr)));
+        }
+        let tok = self.peek().cloned();
+        self.at += 1;
+        match tok {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::Ident(name)) => ident(&name),
+            Some(tok) => Err(format!("unexpected `{tok}`")),
+            None => Err("unexpected end of expression".to_owned()),
+        }
+    }
+}
+
+fn ident(name: &str) -> Result<Expr, String> {
+    use Register::*;
+    Ok(match name {
+        "ax" => Expr::Reg(Ax),
+        "bx" => Expr::Reg(Bx),
+        "cx" => Expr::Reg(Cx),
+        "dx" => Expr::Reg(Dx),
+        "si" => Expr::Reg(Si),
+        "di" => Expr::Reg(Di),
+        "sp" => Expr::Reg(Sp),
+        "bp" => Expr::Reg(Bp),
+        "ip" => Expr::Reg(Ip),
+        "sf" => Expr::Flag(Flag::Sf),
+        "cf" => Expr::Flag(Flag::Cf),
+        "zf" => Expr::Flag(Flag::Zf),
+        "of" => Expr::Flag(Flag::Of),
+        "hits" => Expr::Hits,
+        "cycles" => Expr::Cycles,
+        _ => return Err(format!("unknown name `{name}`")),
+    })
+}
@@ -0,0 +1,59 @@
+use expect_test::expect;
+
+use super::Expr;
+use crate::parse::Parser;
+
+#[test]
+fn eval() {
+    let mut vm = Parser::new("mov cx, 500\npush 11\ncmp cx, 500").parse();
+    vm.run();
+    let out = [
+        "cx == 500 && [sp] > 10",
+        "hits > 1000",
+        "cx + 2 * 3 - 1",
+        "(cx + 2) * 3",
+        "zf && !sf",
+        "[sp - 1] == 0 || 1 / 0",
+        "1 / 0 || 1",
+        "-1 > 0",
+        "0x10 % 0b11 + 'a'",
+        "cycles",
+        "[0xffffff]",
+    ]
+    .map(|src| {
+        let value = Expr::parse(src).unwrap().eval(&vm, 1000);
+        format!("{src} = {value:?}\n")
+    })
+    .concat();
+    expect![[r#"
+        cx == 500 && [sp] > 10 = Some(1)
+        hits > 1000 = Some(0)
+        cx + 2 * 3 - 1 = Some(505)
+        (cx + 2) * 3 = Some(1506)
+        zf && !sf = Some(1)
+        [sp - 1] == 0 || 1 / 0 = Some(1)
+        1 / 0 || 1 = None
+        -1 > 0 = Some(1)
+        0x10 % 0b11 + 'a' = Some(98)
+        cycles = Some(3)
+        [0xffffff] = None
+    "#]]
+    .assert_eq(&out);
+}
+
+#[test]
+fn errors() {
+    let out = ["cx ==", "(ax", "bx cx", "ex", "1 & 2", "0xz", "'a"]
+        .map(|src| format!("{src}: {}\n", Expr::parse(src).unwrap_err()))
+        .concat();
+    expect![[r#"
+        cx ==: unexpected end of expression
+        (ax: expected `)`
+        bx cx: unexpected `cx`
+        ex: unknown name `ex`
+        1 & 2: unexpected `&`
+        0xz: invalid number `0xz`
+        'a: unterminated character
+    "#]]
+    .assert_eq(&out);
+}
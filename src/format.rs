@@ -0,0 +1,146 @@
+//! reprints source canonically
+//!
+//! labels and directives sit at column 0 and instructions are indented with
+//! their operands in one column. trailing comments on consecutive lines
+//! share a column, comments and strings are kept exactly as written and
+//! formatting twice changes nothing.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::lex::{lexemes, Advance, Lexeme};
+
+#[cfg(test)]
+mod test;
+
+pub const INDENT: usize = 4;
+/// the width of the mnemonic column, the longest mnemonic and some room
+pub const MNEMONIC: usize = 8;
+
+/// a line of output, before its comment is aligned
+struct Line<'a> {
+    /// the indented code, empty on a blank or comment only line
+    code: String,
+    comment: Option<&'a str>,
+}
+
+impl Line<'_> {
+    fn width(&self) -> usize {
+        self.code.chars().count()
+    }
+}
+
+pub fn format(src: &str) -> String {
+    let lexemes = lexemes(src);
+    let mut lines = Vec::new();
+    for line in lexemes.split_inclusive(|(ad, _)| matches!(ad.lex, Lexeme::Eol(_))) {
+        let (code, comment) = match line.split_last() {
+            Some(((ad, text), code)) if ad.lex == Lexeme::Eol(true) => {
+                let comment = text.strip_suffix('\n').unwrap_or(text);
+                (code, Some((comment, ad.span.from == ad.offset)))
+            }
+            Some(((ad, _), code)) if ad.lex == Lexeme::Eol(false) => (code, None),
+            _ => (line, None),
+        };
+        let code = match (code, comment) {
+            // a comment on a line of its own stays at column 0 if it was
+            ([], None | Some((_, true))) => String::new(),
+            ([], Some((_, false))) => " ".repeat(INDENT),
+            (code, _) => format_code(code),
+        };
+        lines.push(Line {
+            code,
+            comment: comment.map(|(c, _)| c),
+        });
+    }
+    // blank lines are kept, but only one at a time and none at either end
+    let blank = |l: &Line| l.code.is_empty() && l.comment.is_none();
+    let mut kept = Vec::<Line>::new();
+    for line in lines {
+        if blank(&line) && kept.last().is_none_or(blank) {
+            continue;
+        }
+        kept.push(line);
+    }
+    if kept.last().is_some_and(blank) {
+        kept.pop();
+    }
+
+    let mut out = String::new();
+    let trailing = |l: &Line| l.comment.is_some() && !l.code.trim().is_empty();
+    let mut rest = &kept[..];
+    while !rest.is_empty() {
+        let run = rest.iter().take_while(|l| trailing(l)).count().max(1);
+        let column = rest[..run].iter().map(|l| l.width() + 1).max().unwrap_or(0);
+        for line in &rest[..run] {
+            out += &line.code;
+            if let Some(comment) = line.comment {
+                if trailing(line) {
+                    out += &" ".repeat(column - line.width());
+                }
+                out += comment;
+            }
+            out.push('\n');
+        }
+        rest = &rest[run..];
+    }
+    out
+}
+
+/// a line's lexemes, without its end, spaced canonically
+fn format_code(code: &[(Advance, &str)]) -> String {
+    let instruction = match code {
+        [(first, _), (second, _), ..] if first.lex == Lexeme::Ident => second.lex != Lexeme::Colon,
+        [(first, _), ..] => first.lex == Lexeme::Ident,
+        [] => false,
+    };
+    let mut out = String::new();
+    if instruction {
+        out += &" ".repeat(INDENT);
+    }
+    for (i, &(ad, text)) in code.iter().enumerate() {
+        if i > 0 {
+            let prev = code[i - 1].0.lex;
+            let space = match (prev, ad.lex) {
+                (_, Lexeme::Comma | Lexeme::CloseBracket | Lexeme::Colon) => 0,
+                (Lexeme::OpenBracket | Lexeme::Plus | Lexeme::Minus, _) => 0,
+                _ if i == 1 && instruction => MNEMONIC.saturating_sub(code[0].1.len()).max(1),
+                _ => 1,
+            };
+            out += &" ".repeat(space);
+        }
+        out += text;
+    }
+    out
+}
+
+/// formats every `.asm` file at `path`, a file or a directory, returning
+/// those that changed. with `check` nothing is written
+pub fn format_path(path: impl AsRef<Path>, check: bool) -> io::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let paths = match path.is_dir() {
+        true => {
+            let mut paths = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            paths.retain(|p| p.extension().is_some_and(|ext| ext == "asm"));
+            paths.sort();
+            paths
+        }
+        false => vec![path.to_owned()],
+    };
+    let mut changed = Vec::new();
+    for path in paths {
+        let src = std::fs::read_to_string(&path)?;
+        let formatted = format(&src);
+        if formatted != src {
+            if !check {
+                std::fs::write(&path, formatted)?;
+            }
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
@@ -0,0 +1,88 @@
+use expect_test::expect;
+
+use super::format;
+use crate::{
+    lex::{lexemes, Lexeme},
+    parse::Parser,
+};
+
+/// everything formatting must keep, line ends aside
+fn texts(src: &str) -> Vec<&str> {
+    lexemes(src)
+        .into_iter()
+        .filter(|(ad, _)| ad.lex != Lexeme::Eol(false))
+        .map(|(_, text)| text.strip_suffix('\n').unwrap_or(text))
+        .collect()
+}
+
+#[test]
+fn canonical() {
+    let src = "
+
+; at the top
+.entry   main
+  .vector 3 ,handler
+main :
+mov ax,1 ; one
+	lea si , cx,sparse  \"a  b\"\t;\ttabs  kept
+  jmp   +2
+
+
+    ; inside
+        cmp [ax], [0x20]
+  je\tmain
+handler:
+iret ; done";
+    let formatted = format(src);
+    expect![[r#"
+        ; at the top
+        .entry main
+        .vector 3, handler
+        main:
+            mov     ax, 1                 ; one
+            lea     si, cx, sparse "a  b" ;	tabs  kept
+            jmp     +2
+
+            ; inside
+            cmp     [ax], [0x20]
+            je      main
+        handler:
+            iret ; done
+    "#]]
+    .assert_eq(&formatted);
+    assert_eq!(texts(src), texts(&formatted));
+    assert_eq!(format(&formatted), formatted);
+    assert_eq!(
+        Parser::new(src).parse().seq,
+        Parser::new(&formatted).parse().seq
+    );
+}
+
+#[test]
+fn euler() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/project-euler");
+    for path in std::fs::read_dir(dir).unwrap() {
+        let path = path.unwrap().path();
+        let src = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(format(&src), src, "{} is not formatted", path.display());
+        // indented with tabs, spaced out and padded with blank lines
+        let messy = src
+            .lines()
+            .map(|line| match line.strip_prefix("    ") {
+                Some(code) if !code.starts_with(';') => {
+                    format!("\t{}\n", code.replacen(' ', "  \t", 1))
+                }
+                _ if !line.trim_start().starts_with(';') => format!("{line} \t\n"),
+                _ => format!("{line}\n"),
+            })
+            .collect::<String>();
+        assert_eq!(format(&format!("\n\n{messy}\n\n\n")), src);
+    }
+}
+
+#[test]
+fn empty() {
+    assert_eq!(format(""), "");
+    assert_eq!(format("\n\n  \n"), "");
+    assert_eq!(format("ret"), "    ret\n");
+}
@@ -0,0 +1,205 @@
+//! checks programs against the results they state in their comments
+//!
+//! ```text
+//! ; expect: dx = 233168
+//! ```
+//!
+//! both sides are [`crate::expr`] expressions, evaluated once the program
+//! has run to its end. a program passes when it ends or exits with 0 and
+//! every expectation holds.
+
+use std::{
+    fmt::Write,
+    io,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{
+    expr::Expr,
+    lex::{BaseLexer, Lexeme, Lexer},
+    panic_message,
+    parse::Parser,
+    watch::Stop,
+};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    /// the zero based line of the comment
+    pub line: u32,
+    pub lhs: Expr,
+    pub rhs: Expr,
+    /// the text after `expect:`
+    pub text: String,
+}
+
+/// every `; expect:` comment in the source
+pub fn expectations(src: &str) -> Result<Vec<Expectation>, String> {
+    let mut lexer = BaseLexer::new(src);
+    let mut out = Vec::new();
+    loop {
+        let ad = Lexer::advance(&mut lexer);
+        match ad.lex {
+            Lexeme::Eof => break,
+            Lexeme::Eol(true) => (),
+            _ => continue,
+        }
+        // a comment at the very end runs one past the source
+        let end = (ad.span.to as usize).min(src.len());
+        let comment = src[ad.span.from as usize + 1..end].trim();
+        let Some(text) = comment.strip_prefix("expect:") else {
+            continue;
+        };
+        let text = text.trim();
+        let err = |e: String| format!("line {}: {e}", ad.line + 1);
+        let Some(eq) = assignment(text) else {
+            return Err(err(format!("expected `lhs = rhs`, found `{text}`")));
+        };
+        out.push(Expectation {
+            line: ad.line,
+            lhs: Expr::parse(&text[..eq]).map_err(err)?,
+            rhs: Expr::parse(&text[eq + 1..]).map_err(err)?,
+            text: text.to_owned(),
+        });
+    }
+    Ok(out)
+}
+
+/// the index of the first `=` that is not part of a comparison
+fn assignment(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    (0..bytes.len()).find(|&i| {
+        bytes[i] == b'='
+            && !matches!(
+                i.checked_sub(1).map(|p| bytes[p]),
+                Some(b'=' | b'!' | b'<' | b'>')
+            )
+            && bytes.get(i + 1) != Some(&b'=')
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub name: String,
+    /// instructions executed, zero if the program never ran
+    pub cycles: u64,
+    pub expectations: usize,
+    /// why the program failed, empty when it passed
+    pub failures: Vec<String>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// assembles and runs `src`, checking its expectations
+pub fn check(name: &str, src: &str) -> Outcome {
+    let mut outcome = Outcome {
+        name: name.to_owned(),
+        cycles: 0,
+        expectations: 0,
+        failures: Vec::new(),
+    };
+    let expectations = match expectations(src) {
+        Ok(e) if e.is_empty() => {
+            outcome.failures.push("no expectations".to_owned());
+            return outcome;
+        }
+        Ok(e) => e,
+        Err(e) => {
+            outcome.failures.push(e);
+            return outcome;
+        }
+    };
+    outcome.expectations = expectations.len();
+    // errors in the vm are panics, one program failing should not stop the
+    // others from being checked
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut vm = Parser::new(src).parse();
+        let stop = vm.run();
+        (vm, stop)
+    }));
+    let (vm, stop) = match run {
+        Ok(run) => run,
+        Err(err) => {
+            let msg = panic_message(err.as_ref());
+            outcome.failures.push(format!("panicked: {msg}"));
+            return outcome;
+        }
+    };
+    outcome.cycles = vm.cycles;
+    if !matches!(stop, Stop::End | Stop::Exit(0)) {
+        outcome.failures.push(stop.to_string());
+    }
+    for e in expectations {
+        let (lhs, rhs) = (e.lhs.eval(&vm, 0), e.rhs.eval(&vm, 0));
+        if lhs.is_none() || lhs != rhs {
+            let found = lhs.map_or_else(|| "undefined".to_owned(), |v| v.to_string());
+            outcome
+                .failures
+                .push(format!("line {}: {}, found {found}", e.line + 1, e.text));
+        }
+    }
+    outcome
+}
+
+/// checks every `.asm` file in `dir`, sorted by name
+pub fn check_dir(dir: impl AsRef<Path>) -> io::Result<Vec<Outcome>> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|p| p.extension().is_some_and(|ext| ext == "asm"));
+    paths.sort();
+    check_paths(&paths)
+}
+
+/// checks each file on a thread of its own, in order
+pub fn check_paths(paths: &[PathBuf]) -> io::Result<Vec<Outcome>> {
+    let files = paths
+        .iter()
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            Ok((name, std::fs::read_to_string(path)?))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    // a panic in a program is caught by `check`, so the threads never do
+    Ok(thread::scope(|s| {
+        files
+            .iter()
+            .map(|(name, src)| s.spawn(|| check(name, src)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|h| h.join().expect("checking panicked"))
+            .collect()
+    }))
+}
+
+/// a pass/fail table with instruction counts, failures listed under their
+/// program
+pub fn table(outcomes: &[Outcome]) -> String {
+    let width = outcomes
+        .iter()
+        .map(|o| o.name.len())
+        .fold("program".len(), usize::max);
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<width$}  result  {:>12}", "program", "cycles");
+    for o in outcomes {
+        let result = match o.passed() {
+            true => "pass",
+            false => "FAIL",
+        };
+        let _ = writeln!(out, "{:<width$}  {result:<6}  {:>12}", o.name, o.cycles);
+        for failure in &o.failures {
+            let _ = writeln!(out, "    {failure}");
+        }
+    }
+    let passed = outcomes.iter().filter(|o| o.passed()).count();
+    let _ = writeln!(out, "\n{passed} passed, {} failed", outcomes.len() - passed);
+    out
+}
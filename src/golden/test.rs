@@ -0,0 +1,71 @@
+use expect_test::expect;
+
+use super::{check, check_dir, check_paths, table};
+
+/// too slow to run without optimizations, see `smaller_bound`
+const SLOW: &[&str] = &["problem-5.asm"];
+
+#[test]
+fn euler() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/project-euler");
+    let outcomes = match cfg!(debug_assertions) {
+        true => {
+            let mut paths = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "asm"))
+                .filter(|path| !SLOW.iter().any(|slow| path.ends_with(slow)))
+                .collect::<Vec<_>>();
+            paths.sort();
+            check_paths(&paths).unwrap()
+        }
+        false => check_dir(dir).unwrap(),
+    };
+    assert!(outcomes.len() >= 8);
+    assert!(outcomes.iter().all(|o| o.passed()), "{}", table(&outcomes));
+}
+
+#[test]
+fn smaller_bound() {
+    // problem-5 up to 10 rather than 20, quick enough without optimizations
+    let src = include_str!("../../project-euler/problem-5.asm")
+        .replace("20", "10")
+        .replace("232792560", "2520");
+    let outcome = check("problem-5.asm", &src);
+    assert!(outcome.passed(), "{}", table(&[outcome]));
+}
+
+#[test]
+fn failures() {
+    let outcomes = [
+        check(
+            "pass.asm",
+            "; expect: ax = 3\n; expect: [sp] == 7 = 1\npush 7\nmov ax, 3",
+        ),
+        check(
+            "wrong.asm",
+            "mov ax, 2 ; expect: ax = 3\nmov bx, 1 ; expect: bx = 0x1",
+        ),
+        check("exit.asm", "; expect: ax = 0\nexit 4"),
+        check("none.asm", "mov ax, 1"),
+        check("syntax.asm", "; expect: ax == 1"),
+        check("panic.asm", "; expect: ax = 0\npop ax"),
+    ];
+    expect![[r#"
+        program     result        cycles
+        pass.asm    pass               2
+        wrong.asm   FAIL               2
+            line 1: ax = 3, found 2
+        exit.asm    FAIL               1
+            exited with 4
+        none.asm    FAIL               0
+            no expectations
+        syntax.asm  FAIL               0
+            line 1: expected `lhs = rhs`, found `ax == 1`
+        panic.asm   FAIL               0
+            panicked: used entire available memory; overflow.
+
+        1 passed, 5 failed
+    "#]]
+    .assert_eq(&table(&outcomes));
+}
@@ -0,0 +1,121 @@
+//! an undo log for stepping backwards
+//!
+//! while recording, every step logs the registers and flags it started
+//! with, along with the previous value of each memory word and label it
+//! overwrote. undoing a step puts all of them back.
+
+use std::collections::VecDeque;
+
+use string_interner::DefaultSymbol;
+
+use crate::{trace::Tracer, FlagSet, HexSize, HexVm, RegisterSet};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    reg: RegisterSet,
+    flg: FlagSet,
+    /// `(address, old word)` in the order they were written
+    mem: Vec<(HexSize, HexSize)>,
+    labels: Vec<(DefaultSymbol, HexSize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    /// steps kept before the oldest is dropped
+    pub limit: usize,
+    entries: VecDeque<Entry>,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// steps that can be undone
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn begin(&mut self, reg: RegisterSet, flg: FlagSet) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.entries.len() == self.limit {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            reg,
+            flg,
+            mem: Vec::new(),
+            labels: Vec::new(),
+        });
+    }
+
+    pub(crate) fn mem_write(&mut self, add: HexSize, old: HexSize) {
+        if let Some(entry) = self.entries.back_mut() {
+            entry.mem.push((add, old));
+        }
+    }
+
+    pub(crate) fn label_write(&mut self, sym: DefaultSymbol, old: HexSize) {
+        if let Some(entry) = self.entries.back_mut() {
+            entry.labels.push((sym, old));
+        }
+    }
+
+    /// forgets the last step without undoing it
+    pub(crate) fn discard(&mut self) {
+        self.entries.pop_back();
+    }
+}
+
+impl<T: Tracer> HexVm<T> {
+    /// starts logging every step, keeping at most `limit` of them
+    pub fn record(&mut self, limit: usize) {
+        self.history = Some(History::new(limit));
+    }
+
+    /// undoes the last recorded step, returning false when there is none
+    pub fn step_back(&mut self) -> bool {
+        let Some(entry) = self.history.as_mut().and_then(|h| h.entries.pop_back()) else {
+            return false;
+        };
+        for &(add, old) in entry.mem.iter().rev() {
+            self.mem[add as usize] = old;
+        }
+        for &(sym, old) in entry.labels.iter().rev() {
+            self.labels.insert(sym, old);
+        }
+        self.reg = entry.reg;
+        self.flg = entry.flg;
+        self.cycles -= 1;
+        // only the last step can have stopped the program
+        self.exit = None;
+        true
+    }
+
+    /// steps back until `pred` holds, returning false if the history ran
+    /// out first
+    pub fn run_back_until(&mut self, mut pred: impl FnMut(&Self) -> bool) -> bool {
+        while self.step_back() {
+            if pred(self) {
+                return true;
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,49 @@
+use crate::parse::Parser;
+
+const SRC: &str = "
+    mov cx, 0
+    mov dx, 0
+loop:
+    inc cx
+    push cx
+    add dx, cx
+    cmp cx, 6
+    jl loop
+    mov ax, [sp]
+    mov dx, 0
+";
+
+#[test]
+fn back_to_write() {
+    let mut vm = Parser::new(SRC).parse();
+    vm.record(usize::MAX);
+    let start = vm.snapshot();
+    vm.run();
+    assert_eq!(vm.reg.dx, 0);
+
+    // the instruction that zeroed dx
+    assert!(vm.run_back_until(|vm| vm.reg.dx != 0));
+    assert_eq!((vm.reg.ip, vm.reg.dx), (8, 21));
+    // the last inc
+    assert!(vm.run_back_until(|vm| vm.reg.cx != 6));
+    assert_eq!((vm.reg.ip, vm.reg.cx, vm.cycles), (2, 5, 27));
+    assert_eq!(&vm.mem[vm.reg.sp as usize..], [5, 4, 3, 2, 1]);
+
+    while vm.step_back() {}
+    assert_eq!(vm.snapshot(), start);
+    assert!(!vm.run_back_until(|_| true));
+
+    // replaying gives the same run again
+    vm.run();
+    assert_eq!((vm.reg.ax, vm.reg.dx, vm.cycles), (6, 0, 34));
+}
+
+#[test]
+fn limit() {
+    let mut vm = Parser::new(SRC).parse();
+    vm.record(4);
+    vm.run();
+    assert_eq!(vm.history.as_ref().unwrap().len(), 4);
+    assert!(!vm.run_back_until(|vm| vm.reg.cx != 6));
+    assert_eq!((vm.reg.ip, vm.cycles), (5, 30));
+}
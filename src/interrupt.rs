@@ -0,0 +1,99 @@
+//! software interrupts
+//!
+//! `int n` first looks for a handler the host registered for `n`, which
+//! runs in place and may change registers. otherwise entry `n` of the
+//! vector table, set with `.vector n, label`, is used: the flags word and
+//! the return address are pushed and `iret` pops them again.
+//!
+//! the vector table is read-only and sits in the static area right after
+//! the strings, an unset entry holds `HexSize::MAX`.
+
+use std::fmt;
+
+use crate::{trace::Tracer, HexSize, HexVm, RegisterSet, Static};
+
+#[cfg(test)]
+mod test;
+
+/// the most entries the vector table can have
+pub const VECTORS: usize = 256;
+
+/// a host handler, given the registers and a view of memory
+pub type Handler = Box<dyn FnMut(&mut RegisterSet, &[HexSize])>;
+
+/// the host handlers by interrupt number, compared and printed by their
+/// numbers only
+#[derive(Default)]
+pub struct Interrupts {
+    handlers: Vec<(HexSize, Handler)>,
+}
+
+impl Interrupts {
+    /// the numbers with a host handler
+    pub fn numbers(&self) -> impl Iterator<Item = HexSize> + '_ {
+        self.handlers.iter().map(|&(n, _)| n)
+    }
+
+    fn get_mut(&mut self, n: HexSize) -> Option<&mut Handler> {
+        self.handlers
+            .iter_mut()
+            .find(|(at, _)| *at == n)
+            .map(|(_, h)| h)
+    }
+}
+
+impl fmt::Debug for Interrupts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.numbers()).finish()
+    }
+}
+
+impl PartialEq for Interrupts {
+    fn eq(&self, other: &Self) -> bool {
+        self.numbers().eq(other.numbers())
+    }
+}
+
+impl Eq for Interrupts {}
+
+impl<T: Tracer> HexVm<T> {
+    /// registers `handler` for `int n`, replacing any earlier one
+    pub fn handle(
+        &mut self,
+        n: HexSize,
+        handler: impl FnMut(&mut RegisterSet, &[HexSize]) + 'static,
+    ) {
+        match self.interrupts.get_mut(n) {
+            Some(h) => *h = Box::new(handler),
+            None => self.interrupts.handlers.push((n, Box::new(handler))),
+        }
+    }
+
+    /// the first address of the vector table
+    pub fn ivt(&self) -> HexSize {
+        self.statics.last().map_or(0, Static::end)
+    }
+
+    /// the handler's instruction index for `n` in the vector table
+    pub fn vector(&self, n: HexSize) -> Option<HexSize> {
+        if n >= self.vectors.len() as HexSize {
+            return None;
+        }
+        Some(self.mem[(self.ivt() + n) as usize]).filter(|&at| at != HexSize::MAX)
+    }
+
+    pub(crate) fn interrupt(&mut self, n: HexSize) {
+        if let Some(handler) = self.interrupts.get_mut(n) {
+            handler(&mut self.reg, &self.mem);
+            return;
+        }
+        let Some(at) = self.vector(n) else {
+            panic!("no handler for interrupt {n}")
+        };
+        let from = self.reg.ip;
+        self.push(self.flg.to_word());
+        self.push(from + 1);
+        self.reg.ip = at;
+        self.tracer.call(from, at);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{cell::RefCell, rc::Rc};
+
+use expect_test::expect;
+
+use crate::{parse::Parser, verify::verify, HEX_MEM_SIZE};
+
+#[test]
+fn vector_table() {
+    let mut vm = Parser::new(
+        r#"
+        .vector 3, trap
+            lea si, cx, "ab"
+            cmp cx, 5
+            int 3
+            jl done
+            mov dx, 1
+        done:
+            jmp end
+        trap:
+            inc bx
+            cmp bx, 0
+            iret
+        end:
+            "#,
+    )
+    .parse();
+    assert!(verify(&vm).is_empty());
+    expect![[r#"
+            ivt: 1, static end: 5, vectors: [None, None, None, Some(6)]
+        "#]]
+    .assert_eq(&format!(
+        "ivt: {}, static end: {}, vectors: {:?}\n",
+        vm.ivt(),
+        vm.static_end(),
+        (0..4).map(|n| vm.vector(n)).collect::<Vec<_>>()
+    ));
+    vm.run();
+    // the flags of `cmp cx, 5` survive the handler's `cmp`
+    assert_eq!((vm.reg.bx, vm.reg.dx), (1, 0));
+    assert_eq!(vm.reg.sp, HEX_MEM_SIZE);
+}
+
+#[test]
+fn host_handler() {
+    let mut vm = Parser::new(
+        r#"
+            lea si, cx, sparse "hello"
+            mov ax, 1
+            int 0x80
+            mov bx, ax
+            "#,
+    )
+    .parse();
+    let out = Rc::new(RefCell::new(String::new()));
+    let written = out.clone();
+    // ax = 1 writes `cx` sparse bytes at `si`, returning the count
+    vm.handle(0x80, move |reg, mem| {
+        if reg.ax == 1 {
+            let bytes = &mem[reg.si as usize..(reg.si + reg.cx) as usize];
+            written
+                .borrow_mut()
+                .extend(bytes.iter().map(|&b| b as u8 as char));
+            reg.ax = reg.cx;
+        }
+    });
+    vm.run();
+    assert_eq!(*out.borrow(), "hello");
+    assert_eq!((vm.reg.bx, vm.reg.ip), (5, 4));
+}
+
+#[test]
+#[should_panic = "no handler for interrupt 2"]
+fn missing_handler() {
+    Parser::new(
+        ".vector 1, a
+a:
+int 2",
+    )
+    .parse()
+    .run();
+}
@@ -0,0 +1,49 @@
+use expect_test::expect;
+
+use super::Json;
+
+#[test]
+fn round_trip() {
+    let src = r#" {"id": 1, "params": {"text": "mov ax, 1\n\t\"q\" \\ \u00e9\ud83d\ude00",
+        "list": [true, false, null, -2.5, 1e3, []], "empty": {}}} "#;
+    let json = Json::parse(src).unwrap();
+    assert_eq!(
+        json.at(&["params", "list"])
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .len(),
+        6
+    );
+    assert_eq!(json.get("id").and_then(Json::as_u32), Some(1));
+    expect![[r#"{"id":1,"params":{"text":"mov ax, 1\n\t\"q\" \\ é😀","list":[true,false,null,-2.5,1000,[]],"empty":{}}}"#]]
+        .assert_eq(&json.to_string());
+    assert_eq!(Json::parse(&json.to_string()), Ok(json));
+}
+
+#[test]
+fn errors() {
+    let errors = ["", "[1,", "{\"a\" 1}", "\"abc", "\"\\x\"", "1 2", "tru"]
+        .map(|src| Json::parse(src).unwrap_err() + "\n")
+        .concat();
+    expect![[r#"
+        expected a value at 0
+        expected a value at 3
+        expected `:` at 5
+        unterminated string at 0
+        invalid escape in string at 0
+        trailing characters at 2
+        expected a value at 0
+    "#]]
+    .assert_eq(&errors);
+}
+
+#[test]
+fn words() {
+    let json = Json::from(vec![u64::MAX.into(), i64::MIN.into(), 7u64.into()]);
+    assert_eq!(
+        json.to_string(),
+        "[18446744073709551615,-9223372036854775808,7]"
+    );
+    assert_eq!(Json::from(7u64).as_u32(), Some(7));
+}
@@ -10,8 +10,13 @@ pub enum Lexeme {
     Whitespace,
     Ident,
     Str,
+    Char,
     Comma,
     Colon,
+    /// a `.name` directive
+    Directive,
+    Plus,
+    Minus,
     OpenBracket,
     CloseBracket,
     Digit(DigitBase),
@@ -182,8 +187,16 @@ impl<'a> BaseLexer<'a> {
             '[' => Lexeme::OpenBracket,
             ']' => Lexeme::CloseBracket,
             ':' => Lexeme::Colon,
+            '.' if is_id_start(self.first()) => {
+                self.eat_while(is_id_continue);
+                Lexeme::Directive
+            }
+            '+' => Lexeme::Plus,
+            '-' => Lexeme::Minus,
             // String literal.
             '"' => self.string(),
+            // Character literal.
+            '\'' => self.char(),
             _ => {
                 self.eat_while(is_other);
                 Lexeme::Other
@@ -224,6 +237,19 @@ impl<'a> BaseLexer<'a> {
         }
         Lexeme::Str
     }
+    fn char(&mut self) -> Lexeme {
+        if self.first() == '\\' {
+            // Bump again to skip escaped character.
+            self.bump();
+        }
+        if self.first() != '\n' && !self.is_eof() {
+            self.bump();
+        }
+        if self.first() == '\'' {
+            self.bump();
+        }
+        Lexeme::Char
+    }
 
     // TODO: eventually add floats back in
     fn number(&mut self, first_digit: char) -> DigitBase {
@@ -328,6 +354,27 @@ impl<'a> BaseLexer<'a> {
     }
 }
 
+/// every lexeme but whitespace, with its text
+pub fn lexemes(src: &str) -> Vec<(Advance, &str)> {
+    // spans count chars, slicing needs bytes
+    let bytes = src
+        .char_indices()
+        .map(|(b, _)| b)
+        .chain([src.len()])
+        .collect::<Vec<_>>();
+    let byte = |pos: u32| bytes[(pos as usize).min(bytes.len() - 1)];
+    let mut lexer = BaseLexer::new(src);
+    let mut out = Vec::new();
+    loop {
+        let ad = lexer.advance();
+        match ad.lex {
+            Lexeme::Eof => break out,
+            Lexeme::Whitespace => (),
+            _ => out.push((ad, &src[byte(ad.span.from)..byte(ad.span.to)])),
+        }
+    }
+}
+
 fn is_id_start(first: char) -> bool {
     matches!(first,
     'a'..='z' | 'A'..='Z' | '_'
@@ -366,5 +413,8 @@ const fn ws_not_nl(c: char) -> bool {
 fn is_other(c: char) -> bool {
     !(ws_not_nl(c)
         | is_id_start(c)
-        | matches!(c, '0'..='9' | '\n' | ',' | '[' | ']' | ':' | '"' | ';'))
+        | matches!(
+            c,
+            '0'..='9' | '\n' | ',' | '[' | ']' | ':' | '.' | '+' | '-' | '"' | '\'' | ';'
+        ))
 }
@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, io::Write};
 
 use ahash::AHashMap;
 use string_interner::{DefaultStringInterner, DefaultSymbol};
@@ -12,10 +12,38 @@ pub type IHexSize = i64;
 pub const HEX_MEM_SIZE: HexSize = 0xEEEE;
 pub const MEM_SIZE: usize = HEX_MEM_SIZE as usize;
 
+pub mod build;
+pub mod bytecode;
+pub mod cfg;
+pub mod coverage;
+pub mod debug;
+pub mod device;
+pub mod expr;
+pub mod format;
+pub mod golden;
+pub mod history;
+pub mod interrupt;
+pub mod json;
 pub mod lex;
+pub mod lsp;
+pub mod opt;
 pub mod parse;
+pub mod profile;
+pub mod protect;
+pub mod snapshot;
 pub mod span;
+pub mod trace;
+pub mod verify;
+pub mod watch;
 
+use device::{Devices, Output};
+use history::History;
+use interrupt::Interrupts;
+use protect::Protection;
+use trace::{NoTracer, Tracer};
+use watch::{Access, Target, Watchpoints};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JmpKind {
     Jmp,
     Je,
@@ -26,7 +54,7 @@ pub enum JmpKind {
     Jge,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct FlagSet {
     pub sf: bool,
     pub cf: bool,
@@ -57,6 +85,61 @@ impl FlagSet {
             }
         }
     }
+
+    /// the flags packed as `sf | cf << 1 | zf << 2 | of << 3`
+    pub fn to_word(&self) -> HexSize {
+        self.sf as HexSize
+            | (self.cf as HexSize) << 1
+            | (self.zf as HexSize) << 2
+            | (self.of as HexSize) << 3
+    }
+
+    pub fn from_word(word: HexSize) -> Self {
+        Self {
+            sf: word & 1 != 0,
+            cf: word & 2 != 0,
+            zf: word & 4 != 0,
+            of: word & 8 != 0,
+        }
+    }
+
+    /// whether a jump of the given kind is taken
+    pub fn holds(&self, jmp: JmpKind) -> bool {
+        match jmp {
+            JmpKind::Jmp => true,
+            JmpKind::Je => self.zf,
+            JmpKind::Jne => !self.zf,
+            JmpKind::Jl => !self.zf && self.sf,
+            JmpKind::Jle => self.zf || self.sf,
+            JmpKind::Jg => !self.zf && !self.sf,
+            JmpKind::Jge => self.zf || !self.sf,
+        }
+    }
+
+    fn math(&mut self, a: HexSize, b: HexSize, op: Op) -> HexSize {
+        let v = match op {
+            Op::Add => {
+                let (v, cf) = a.overflowing_add(b);
+                self.cf = cf;
+                self.of = a < b;
+                v
+            }
+            Op::Sub => {
+                let (v, of) = a.overflowing_sub(b);
+                self.of = of;
+                self.cf = a < b;
+                v
+            }
+            Op::Div => a / b,
+            Op::Mul => a.wrapping_mul(b),
+            Op::Mod => a % b,
+        };
+        self.sf = v.leading_ones() > 0;
+        // cf
+        self.zf = v == 0;
+        // of
+        v
+    }
     // fn to_ord(&self) -> Option<Ordering> {
     //     if self.zf {
     //         return Some(Ordering::Greater);
@@ -69,49 +152,193 @@ impl FlagSet {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct HexVm {
+pub struct HexVm<T = NoTracer> {
     pub si: DefaultStringInterner,
     pub labels: AHashMap<DefaultSymbol, HexSize>,
+    pub statics: Vec<Static>,
     pub flg: FlagSet,
     pub reg: RegisterSet,
     pub seq: Vec<Sequence>,
-    pub mem: [HexSize; MEM_SIZE],
+    /// the zero based source line of each instruction, empty when the
+    /// sequence was not parsed
+    pub lines: Vec<u32>,
+    /// the instruction index execution starts at
+    pub entry: HexSize,
+    pub mem: Box<[HexSize]>,
+    /// instructions executed so far
+    pub cycles: u64,
+    /// the undo log, while recording
+    pub history: Option<History>,
+    pub devices: Devices,
+    /// where `print` writes
+    pub output: Output,
+    /// the label of each vector table entry, by interrupt number
+    pub vectors: Vec<Option<DefaultSymbol>>,
+    pub interrupts: Interrupts,
+    pub watch: Watchpoints,
+    /// the segments checked on every access, once protected
+    pub protection: Option<Protection>,
+    /// the cycle count [`HexVm::run`] stops at, if limited
+    pub fuel: Option<u64>,
+    /// the status given to `hlt` or `exit`, once the program stopped
+    pub exit: Option<HexSize>,
+    pub tracer: T,
 }
 
 impl HexVm {
     pub fn new(
         seq: impl Into<Vec<Sequence>>,
         labels: impl Into<AHashMap<DefaultSymbol, HexSize>>,
+    ) -> Self {
+        Self::with_memory(seq, labels, MEM_SIZE)
+    }
+
+    /// a vm with `words` of memory, `sp` and `bp` starting at the top
+    pub fn with_memory(
+        seq: impl Into<Vec<Sequence>>,
+        labels: impl Into<AHashMap<DefaultSymbol, HexSize>>,
+        words: usize,
     ) -> Self {
         Self {
             si: DefaultStringInterner::new(),
             labels: labels.into(),
+            statics: Vec::new(),
             flg: FlagSet::default(),
             reg: RegisterSet {
-                bp: HEX_MEM_SIZE,
-                sp: HEX_MEM_SIZE,
+                bp: words as HexSize,
+                sp: words as HexSize,
                 ..Default::default()
             },
             seq: seq.into(),
-            mem: [0; MEM_SIZE],
+            lines: Vec::new(),
+            entry: 0,
+            mem: vec![0; words].into_boxed_slice(),
+            cycles: 0,
+            history: None,
+            devices: Devices::default(),
+            output: Output::default(),
+            vectors: Vec::new(),
+            interrupts: Interrupts::default(),
+            watch: Watchpoints::default(),
+            protection: None,
+            fuel: None,
+            exit: None,
+            tracer: NoTracer,
+        }
+    }
+}
+
+impl<T: Tracer> HexVm<T> {
+    /// replaces the tracer, keeping all other state
+    pub fn with_tracer<U: Tracer>(self, tracer: U) -> HexVm<U> {
+        HexVm {
+            si: self.si,
+            labels: self.labels,
+            statics: self.statics,
+            flg: self.flg,
+            reg: self.reg,
+            seq: self.seq,
+            lines: self.lines,
+            entry: self.entry,
+            mem: self.mem,
+            cycles: self.cycles,
+            history: self.history,
+            devices: self.devices,
+            output: self.output,
+            vectors: self.vectors,
+            interrupts: self.interrupts,
+            watch: self.watch,
+            protection: self.protection,
+            fuel: self.fuel,
+            exit: self.exit,
+            tracer,
         }
     }
 
-    pub fn run(&mut self) {
-        // tracing::info!("run start");
-        while self.seq.len() > self.reg.ip as usize {
-            self.sequence();
+    /// writes every static string and the vector table into the read-only
+    /// area at the bottom of memory
+    pub fn load_statics(&mut self) {
+        for st in &self.statics {
+            let bytes = self.si.resolve(st.sym).unwrap().as_bytes();
+            let mem = &mut self.mem[st.addr as usize..st.end() as usize];
+            if st.sparse {
+                mem.iter_mut()
+                    .zip(bytes)
+                    .for_each(|(w, &b)| *w = b as HexSize);
+            } else {
+                mem.iter_mut()
+                    .zip(bytes.chunks(HexSize::BITS as usize / 8))
+                    .for_each(|(w, b)| {
+                        let mut o = [0; HexSize::BITS as usize / 8];
+                        o[..b.len()].copy_from_slice(b);
+                        *w = HexSize::from_be_bytes(o);
+                    });
+            }
+        }
+        let ivt = self.ivt() as usize;
+        for (n, sym) in self.vectors.iter().enumerate() {
+            self.mem[ivt + n] = match sym {
+                Some(sym) => match self.labels.get(sym) {
+                    Some(&label) => label,
+                    None => undefined_label(&self.si, *sym),
+                },
+                None => HexSize::MAX,
+            };
+        }
+    }
+
+    /// the first address past the static area
+    pub fn static_end(&self) -> HexSize {
+        self.ivt() + self.vectors.len() as HexSize
+    }
+
+    /// rewrites label and relative targets of jumps and calls into absolute
+    /// instruction indices, so running never has to look up a label. labels
+    /// the program writes to keep their lookup, since a jump must see the
+    /// written index
+    pub fn link(&mut self) {
+        let written = self
+            .seq
+            .iter()
+            .flat_map(Sequence::writes)
+            .filter_map(|add| match add {
+                Address::Ident(sym) => Some(sym),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (i, seq) in self.seq.iter_mut().enumerate() {
+            let Some(target) = seq.target_mut() else {
+                continue;
+            };
+            *target = match *target {
+                Value::Address(Address::Ident(sym)) if written.contains(&sym) => continue,
+                Value::Address(Address::Ident(sym)) => match self.labels.get(&sym) {
+                    Some(&label) => Value::Hex(label),
+                    None => undefined_label(&self.si, sym),
+                },
+                Value::IHex(diff) => Value::Hex((i as HexSize).wrapping_add_signed(diff)),
+                value => value,
+            };
         }
-        // tracing::info!("run end");
     }
 
+    /// runs the instruction at `ip`, looking up any target that has not
+    /// been linked
     // TODO: do overflow handling
-    fn sequence(&mut self) {
+    pub fn step(&mut self) {
         use Sequence::*;
         let old = self.reg.ip;
         let seq = self.seq[self.reg.ip as usize];
+        self.tracer.before(old, &seq, &self.reg, &self.flg);
+        if let Some(history) = &mut self.history {
+            history.begin(self.reg, self.flg);
+        }
+        let before = (self.reg, self.flg, self.exit);
+        if let Some(p) = &mut self.protection {
+            p.begin();
+        }
         match seq {
-            Mov(add, value) => *self.address_mut(add) = self.value(value),
+            Mov(add, value) => self.set(add, self.value(value)),
             Cmp(a, b) => self.flg.do_cmp(self.value(a), self.value(b)),
             Jmp(add) => self.jump_ord(add, JmpKind::Jmp),
             Je(add) => self.jump_ord(add, JmpKind::Je),
@@ -120,19 +347,22 @@ impl HexVm {
             Jle(add) => self.jump_ord(add, JmpKind::Jle),
             Jg(add) => self.jump_ord(add, JmpKind::Jg),
             Jge(add) => self.jump_ord(add, JmpKind::Jge),
-            Call(sym) => {
-                push(&mut self.reg.sp, &mut self.mem, self.reg.ip + 1);
-                self.reg.ip = self.labels.get(&sym).copied().unwrap();
+            Call(value) => {
+                self.push(self.reg.ip + 1);
+                self.reg.ip = self.target(value);
+                self.tracer.call(old, self.reg.ip);
             }
             Ret => {
-                self.reg.ip = pop(&mut self.reg.sp, &mut self.mem);
+                self.reg.ip = self.pop();
+                self.tracer.ret(old, self.reg.ip);
             }
             Push(value) => {
                 let word = self.value(value);
-                push(&mut self.reg.sp, &mut self.mem, word);
+                self.push(word);
             }
             Pop(add) => {
-                *self.address_mut(add) = pop(&mut self.reg.sp, &mut self.mem);
+                let word = self.pop();
+                self.set(add, word);
             }
             // TODO: create signed math
             Inc(add) => self.apply_math(add, 1, Op::Add),
@@ -143,93 +373,96 @@ impl HexVm {
             Div(value) => self.reg.ax = self.math(self.reg.ax, self.value(value), Op::Div),
             Mod(value) => self.reg.ax = self.math(self.reg.ax, self.value(value), Op::Mod),
             // TODO: create actual printing system
-            Str(s) => self
-                .si
-                .resolve(s)
-                .unwrap()
-                .as_bytes()
-                .chunks(HexSize::BITS as usize / 8)
-                .rev()
-                .for_each(|b| {
-                    let mut o = [0; HexSize::BITS as usize / 8];
-                    o[..b.len()].copy_from_slice(b);
-                    push(&mut self.reg.sp, &mut self.mem, HexSize::from_be_bytes(o));
-                }),
-            Sparse(s) => self
-                .si
-                .resolve(s)
-                .unwrap()
-                .as_bytes()
-                .iter()
-                .rev()
-                .for_each(|&b| {
-                    push(&mut self.reg.sp, &mut self.mem, b as HexSize);
-                }),
+            Str(i) | Sparse(i) => {
+                let st = self.statics[i];
+                self.push(st.addr);
+                self.push(st.len);
+            }
+            Lea(add, len, i) => {
+                let st = self.statics[i];
+                self.set(add, st.addr);
+                self.set(len, st.len);
+            }
             Print(add, len) => {
-                let start = self.address(add);
-                let s = String::from_utf8(
-                    self.mem[start as usize..start as usize + (len as usize).div_ceil(8)]
-                        .iter()
-                        .flat_map(|&ch| ch.to_be_bytes())
-                        .take(len as usize)
-                        .collect::<Vec<_>>(),
-                )
-                .expect("invalid utf8");
-                print!("{s}");
+                let s = mem_str(&self.mem, self.address(add), len);
+                write!(self.output, "{s}").expect("unable to write output");
+            }
+            Int(value) => self.interrupt(self.value(value)),
+            Iret => {
+                self.reg.ip = self.pop();
+                self.flg = FlagSet::from_word(self.pop());
+                self.tracer.ret(old, self.reg.ip);
             }
+            Hlt => self.halt(0),
+            Exit(value) => self.halt(self.value(value)),
         }
-        tracing::info!("exec {}", self.reg.ip);
-        tracing::info!("{seq:?}");
-        tracing::info!("{:?}", self.reg);
-        tracing::info!("{:?}", self.flg);
-        tracing::info!("end  {}, mid-change {}", self.reg.ip, (old != self.reg.ip));
         self.reg.ip += (old == self.reg.ip) as HexSize;
+        if self.settle(before) {
+            return;
+        }
+        self.cycles += 1;
+        self.tracer.after(old, &seq, &self.reg, &self.flg);
     }
 
-    fn jump_ord(&mut self, value: Value, jmp: JmpKind) {
-        let val = || match value {
+    /// stops the program by moving `ip` to its end
+    fn halt(&mut self, status: HexSize) {
+        self.exit = Some(status);
+        self.reg.ip = self.seq.len() as HexSize;
+    }
+
+    fn push(&mut self, word: HexSize) {
+        if let Some(p) = &self.protection {
+            if !p.check_stack(self.reg.sp.wrapping_sub(1), protect::Perm::Write) {
+                return;
+            }
+        }
+        if self.reg.sp <= self.static_end() {
+            panic!("used entire available memory; underflow.")
+        }
+        self.reg.sp -= 1;
+        let old = std::mem::replace(&mut self.mem[self.reg.sp as usize], word);
+        self.watch
+            .access(Target::Mem(self.reg.sp), Access::Write, old, word);
+        self.mem_written(self.reg.sp, old, word);
+    }
+
+    fn pop(&mut self) -> HexSize {
+        if let Some(p) = &self.protection {
+            if !p.check_stack(self.reg.sp, protect::Perm::Read) {
+                return 0;
+            }
+        }
+        if self.reg.sp as usize >= self.mem.len() {
+            panic!("used entire available memory; overflow.")
+        }
+        let val = self.mem[self.reg.sp as usize];
+        self.watch
+            .access(Target::Mem(self.reg.sp), Access::Read, val, val);
+        self.reg.sp += 1;
+        val
+    }
+
+    fn target(&self, value: Value) -> HexSize {
+        match value {
+            Value::Hex(hx) => hx,
             Value::IHex(diff) => self.reg.ip.wrapping_add_signed(diff),
             value => self.value(value),
-        };
-        self.reg.ip = match jmp {
-            JmpKind::Jmp => val(),
-            JmpKind::Je if self.flg.zf => val(),
-            JmpKind::Jne if !self.flg.zf => val(),
-            JmpKind::Jl if !self.flg.zf && self.flg.sf => val(),
-            JmpKind::Jle if self.flg.zf || self.flg.sf => val(),
-            JmpKind::Jg if !self.flg.zf && !self.flg.sf => val(),
-            JmpKind::Jge if self.flg.zf || !self.flg.sf => val(),
-            _ => self.reg.ip,
-        };
+        }
+    }
+
+    fn jump_ord(&mut self, value: Value, jmp: JmpKind) {
+        if self.flg.holds(jmp) {
+            self.reg.ip = self.target(value);
+        }
     }
 
     fn apply_math(&mut self, add: Address, b: HexSize, op: Op) {
-        *self.address_mut(add) = self.math(self.address(add), b, op);
+        let v = self.math(self.address(add), b, op);
+        self.set(add, v);
     }
 
     fn math(&mut self, a: HexSize, b: HexSize, op: Op) -> HexSize {
-        let v = match op {
-            Op::Add => {
-                let (v, cf) = a.overflowing_add(b);
-                self.flg.cf = cf;
-                self.flg.of = a < b;
-                v
-            }
-            Op::Sub => {
-                let (v, of) = a.overflowing_sub(b);
-                self.flg.of = of;
-                self.flg.cf = a < b;
-                v
-            }
-            Op::Div => a / b,
-            Op::Mul => a.wrapping_mul(b),
-            Op::Mod => a % b,
-        };
-        self.flg.sf = v.leading_ones() > 0;
-        // cf
-        self.flg.zf = v == 0;
-        // of
-        v
+        self.flg.math(a, b, op)
     }
 
     // #[allow(unused)]
@@ -249,18 +482,10 @@ impl HexVm {
     // }
 
     fn reg(&self, reg: Register) -> HexSize {
-        use Register::*;
-        match reg {
-            Ax => self.reg.ax,
-            Bx => self.reg.bx,
-            Cx => self.reg.cx,
-            Dx => self.reg.dx,
-            Si => self.reg.si,
-            Di => self.reg.di,
-            Sp => self.reg.sp,
-            Bp => self.reg.bp,
-            Ip => self.reg.ip,
-        }
+        let word = self.reg.get(reg);
+        self.watch
+            .access(Target::Reg(reg), Access::Read, word, word);
+        word
     }
 
     fn reg_mut(&mut self, reg: Register) -> &mut HexSize {
@@ -279,11 +504,50 @@ impl HexVm {
     }
 
     fn mem_at(&self, add: HexSize) -> HexSize {
-        self.mem[add as usize]
+        if let Some(p) = &self.protection {
+            if !p.check(add, protect::Perm::Read) {
+                return 0;
+            }
+        }
+        if !self.devices.is_empty() {
+            if let Some(word) = self.devices.read(add, self.cycles) {
+                return word;
+            }
+        }
+        let word = self.mem[add as usize];
+        self.watch
+            .access(Target::Mem(add), Access::Read, word, word);
+        word
+    }
+
+    fn store(&mut self, add: HexSize, word: HexSize) {
+        if let Some(p) = &self.protection {
+            if !p.check(add, protect::Perm::Write) {
+                return;
+            }
+        }
+        if add < self.static_end() {
+            panic!("write to read-only static memory at {add}")
+        }
+        if !self.devices.is_empty() && self.devices.write(add, word, self.cycles) {
+            return;
+        }
+        let old = std::mem::replace(&mut self.mem[add as usize], word);
+        self.watch
+            .access(Target::Mem(add), Access::Write, old, word);
+        self.mem_written(add, old, word);
     }
 
-    fn mem_mut(&mut self, add: HexSize) -> &mut HexSize {
-        &mut self.mem[add as usize]
+    /// logs a write to memory, the tracer hears of it once the step is
+    /// known not to fault
+    fn mem_written(&mut self, add: HexSize, old: HexSize, new: HexSize) {
+        if let Some(history) = &mut self.history {
+            history.mem_write(add, old);
+        }
+        match &mut self.protection {
+            Some(p) => p.mem_write(add, old, new),
+            None => self.tracer.mem_write(add, old, new),
+        }
     }
 
     fn address(&self, add: Address) -> HexSize {
@@ -292,17 +556,34 @@ impl HexVm {
             Register(r, d) if d => self.mem_at(self.reg(r)),
             Register(r, _) => self.reg(r),
             Stack(add) => self.mem_at(add),
-            Ident(sym) => self.labels.get(&sym).copied().unwrap(),
+            Ident(sym) => match self.labels.get(&sym) {
+                Some(&label) => label,
+                None => undefined_label(&self.si, sym),
+            },
         }
     }
 
-    fn address_mut(&mut self, add: Address) -> &mut HexSize {
+    fn set(&mut self, add: Address, word: HexSize) {
         use Address::*;
         match add {
-            Register(r, d) if d => self.mem_mut(self.reg(r)),
-            Register(r, _) => self.reg_mut(r),
-            Stack(add) => self.mem_mut(add),
-            Ident(sym) => self.labels.get_mut(&sym).unwrap(),
+            Register(r, d) if d => self.store(self.reg(r), word),
+            Register(r, _) => {
+                let old = std::mem::replace(self.reg_mut(r), word);
+                self.watch.access(Target::Reg(r), Access::Write, old, word);
+            }
+            Stack(add) => self.store(add, word),
+            Ident(sym) => {
+                let Some(label) = self.labels.get_mut(&sym) else {
+                    undefined_label(&self.si, sym)
+                };
+                let old = std::mem::replace(label, word);
+                if let Some(history) = &mut self.history {
+                    history.label_write(sym, old);
+                }
+                if let Some(p) = &mut self.protection {
+                    p.label_write(sym, old);
+                }
+            }
         }
     }
 
@@ -342,6 +623,23 @@ pub struct RegisterSet {
     pub ip: HexSize,
 }
 
+impl RegisterSet {
+    pub fn get(&self, reg: Register) -> HexSize {
+        use Register::*;
+        match reg {
+            Ax => self.ax,
+            Bx => self.bx,
+            Cx => self.cx,
+            Dx => self.dx,
+            Si => self.si,
+            Di => self.di,
+            Sp => self.sp,
+            Bp => self.bp,
+            Ip => self.ip,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Sequence {
     Mov(Address, Value),
@@ -353,7 +651,7 @@ pub enum Sequence {
     Jle(Value),
     Jg(Value),
     Jge(Value),
-    Call(DefaultSymbol),
+    Call(Value),
     Ret,
     Push(Value),
     Pop(Address),
@@ -366,14 +664,61 @@ pub enum Sequence {
     Div(Value),
     Mod(Value),
     // Pow(Value),
-    Str(DefaultSymbol),
-    Sparse(DefaultSymbol),
+    /// push the address, then the byte length, of a packed static string
+    Str(usize),
+    /// push the address, then the byte length, of a sparse static string
+    Sparse(usize),
+    /// load the address and byte length of a static string
+    Lea(Address, Address, usize),
     Print(Address, HexSize),
+    /// raise a software interrupt
+    Int(Value),
+    /// return from an interrupt handler in the vector table
+    Iret,
+    /// stop with an exit status of 0
+    Hlt,
+    /// stop with the value as the exit status
+    Exit(Value),
     // Dyn      = allocate  dynamic
     // Down     = delete    dynamic
 }
 
 impl Sequence {
+    /// the name the assembler knows the instruction by
+    pub fn mnemonic(&self) -> &'static str {
+        use Sequence::*;
+        match self {
+            Mov(..) => "mov",
+            Cmp(..) => "cmp",
+            Jmp(_) => "jmp",
+            Je(_) => "je",
+            Jne(_) => "jne",
+            Jl(_) => "jl",
+            Jle(_) => "jle",
+            Jg(_) => "jg",
+            Jge(_) => "jge",
+            Call(_) => "call",
+            Ret => "ret",
+            Push(_) => "push",
+            Pop(_) => "pop",
+            Add(..) => "add",
+            Sub(..) => "sub",
+            Inc(_) => "inc",
+            Dec(_) => "dec",
+            Mul(_) => "mul",
+            Div(_) => "div",
+            Mod(_) => "mod",
+            Str(_) => "str",
+            Sparse(_) => "sparse",
+            Lea(..) => "lea",
+            Print(..) => "print",
+            Int(_) => "int",
+            Iret => "iret",
+            Hlt => "hlt",
+            Exit(_) => "exit",
+        }
+    }
+
     pub fn is_jump(&self) -> bool {
         use Sequence::*;
         matches!(
@@ -381,6 +726,82 @@ impl Sequence {
             Jmp(_) | Je(_) | Jne(_) | Jl(_) | Jle(_) | Jg(_) | Jge(_)
         )
     }
+
+    /// the condition of a jump, if this is one
+    pub fn jmp_kind(&self) -> Option<JmpKind> {
+        use Sequence::*;
+        Some(match self {
+            Jmp(_) => JmpKind::Jmp,
+            Je(_) => JmpKind::Je,
+            Jne(_) => JmpKind::Jne,
+            Jl(_) => JmpKind::Jl,
+            Jle(_) => JmpKind::Jle,
+            Jg(_) => JmpKind::Jg,
+            Jge(_) => JmpKind::Jge,
+            _ => return None,
+        })
+    }
+
+    /// the jump or call target, if any
+    pub fn target(&self) -> Option<Value> {
+        use Sequence::*;
+        match *self {
+            Jmp(v) | Je(v) | Jne(v) | Jl(v) | Jle(v) | Jg(v) | Jge(v) | Call(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn target_mut(&mut self) -> Option<&mut Value> {
+        use Sequence::*;
+        match self {
+            Jmp(v) | Je(v) | Jne(v) | Jl(v) | Jle(v) | Jg(v) | Jge(v) | Call(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// the addresses the instruction writes
+    pub fn writes(&self) -> impl Iterator<Item = Address> {
+        use Sequence::*;
+        let (a, b) = match *self {
+            Mov(a, _) | Pop(a) | Inc(a) | Dec(a) | Add(a, _) | Sub(a, _) => (Some(a), None),
+            Lea(a, b, _) => (Some(a), Some(b)),
+            _ => (None, None),
+        };
+        a.into_iter().chain(b)
+    }
+
+    pub fn jump_value(&self) -> Option<Value> {
+        use Sequence::*;
+        match *self {
+            Jmp(v) | Je(v) | Jne(v) | Jl(v) | Jle(v) | Jg(v) | Jge(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// a string literal placed once in the read-only static area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Static {
+    pub sym: DefaultSymbol,
+    /// one byte per word rather than packed big-endian
+    pub sparse: bool,
+    pub addr: HexSize,
+    /// length in bytes
+    pub len: HexSize,
+}
+
+impl Static {
+    pub fn words(&self) -> HexSize {
+        if self.sparse {
+            self.len
+        } else {
+            self.len.div_ceil(HexSize::BITS as HexSize / 8)
+        }
+    }
+
+    pub fn end(&self) -> HexSize {
+        self.addr + self.words()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -397,8 +818,29 @@ pub enum Value {
     IHex(IHexSize),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Register {
+/// declares [`Register`] with its doc comments, which [`Register::doc`]
+/// returns too
+macro_rules! registers {
+    ($(#[doc = $doc:literal] $name:ident,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Register {
+            $(#[doc = $doc] $name,)*
+        }
+
+        impl Register {
+            pub const ALL: [Register; 9] = [$(Register::$name),*];
+
+            /// what the register is for, the doc comment on its variant
+            pub fn doc(self) -> &'static str {
+                match self {
+                    $(Register::$name => $doc.trim(),)*
+                }
+            }
+        }
+    };
+}
+
+registers! {
     /// accumulator, volatile, return value
     Ax,
     /// base, stable, storage
@@ -446,21 +888,31 @@ impl From<Address> for Value {
     }
 }
 
-fn push(sp: &mut HexSize, mem: &mut [HexSize], word: HexSize) {
-    if *sp == 0 {
-        panic!("used entire available memory; underflow.")
-    }
-    *sp -= 1;
-    mem[*sp as usize] = word;
+/// reads `len` bytes packed big-endian from `start`
+fn mem_str(mem: &[HexSize], start: HexSize, len: HexSize) -> String {
+    String::from_utf8(
+        mem[start as usize..start as usize + (len as usize).div_ceil(8)]
+            .iter()
+            .flat_map(|&ch| ch.to_be_bytes())
+            .take(len as usize)
+            .collect::<Vec<_>>(),
+    )
+    .expect("invalid utf8")
 }
 
-fn pop(sp: &mut HexSize, mem: &mut [HexSize]) -> HexSize {
-    if *sp >= HEX_MEM_SIZE {
-        panic!("used entire available memory; overflow.")
-    }
-    let val = mem[*sp as usize];
-    *sp += 1;
-    val
+pub(crate) fn undefined_label(si: &DefaultStringInterner, sym: DefaultSymbol) -> ! {
+    panic!(
+        "undefined label: {}",
+        si.resolve(sym).unwrap_or("<unknown>")
+    )
+}
+
+/// the message a panic was raised with
+pub(crate) fn panic_message(err: &(dyn std::any::Any + Send)) -> &str {
+    err.downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| err.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown error")
 }
 
 #[allow(unused)]
@@ -0,0 +1,716 @@
+//! a language server for the assembly dialect, speaking json-rpc over stdio
+//!
+//! documents are synced whole on every change. diagnostics come from the
+//! parser, from labels used but never defined and from [`crate::verify`].
+//! labels have go to definition and find references, mnemonics, registers
+//! and labels have hover docs, and all of them complete.
+//!
+//! positions count chars rather than utf-16 units, the two only differ
+//! outside the basic plane.
+
+use std::{
+    cell::Cell,
+    io::{self, BufRead, Write},
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    thread,
+};
+
+use ahash::AHashMap;
+
+use crate::{
+    json::Json,
+    lex::{lexemes, Advance, BaseLexer, Lexeme, Lexer},
+    panic_message,
+    parse::Parser,
+    verify, Register,
+};
+
+#[cfg(test)]
+mod test;
+
+/// each mnemonic with its syntax and what it does
+pub const MNEMONICS: &[(&str, &str, &str)] = &[
+    (
+        "mov",
+        "mov dst, value",
+        "copy a value into a register or memory",
+    ),
+    ("cmp", "cmp a, b", "compare two values, setting the flags"),
+    (
+        "jmp",
+        "jmp target",
+        "jump to a label, an index or a relative `+n`",
+    ),
+    ("je", "je target", "jump if equal"),
+    ("jne", "jne target", "jump if not equal"),
+    ("jl", "jl target", "jump if less"),
+    ("jle", "jle target", "jump if less or equal"),
+    ("jg", "jg target", "jump if greater"),
+    ("jge", "jge target", "jump if greater or equal"),
+    (
+        "call",
+        "call label",
+        "push the return address and jump to a label",
+    ),
+    ("ret", "ret", "pop the return address and jump to it"),
+    ("push", "push value", "push a value onto the stack"),
+    (
+        "pop",
+        "pop dst",
+        "pop the top of the stack into a register or memory",
+    ),
+    (
+        "add",
+        "add dst, value",
+        "add a value to a register or memory",
+    ),
+    (
+        "sub",
+        "sub dst, value",
+        "subtract a value from a register or memory",
+    ),
+    ("inc", "inc dst", "add one to a register or memory"),
+    ("dec", "dec dst", "subtract one from a register or memory"),
+    ("mul", "mul value", "multiply `ax` by a value"),
+    ("div", "div value", "divide `ax` by a value"),
+    ("mod", "mod value", "set `ax` to its remainder by a value"),
+    (
+        "str",
+        "str \"text\"",
+        "push the address, then the byte length, of a packed static string",
+    ),
+    (
+        "sparse",
+        "sparse \"text\"",
+        "push the address, then the byte length, of a sparse static string",
+    ),
+    (
+        "lea",
+        "lea dst, len, [sparse] \"text\"",
+        "load the address and byte length of a static string",
+    ),
+    (
+        "print",
+        "print addr, len",
+        "print `len` bytes packed from `addr`",
+    ),
+    ("int", "int n", "raise a software interrupt"),
+    (
+        "iret",
+        "iret",
+        "return from an interrupt handler in the vector table",
+    ),
+    ("hlt", "hlt", "stop with an exit status of 0"),
+    (
+        "exit",
+        "exit value",
+        "stop with the value as the exit status",
+    ),
+];
+
+/// each directive with its syntax and what it does
+pub const DIRECTIVES: &[(&str, &str, &str)] = &[
+    (
+        ".vector",
+        ".vector n, label",
+        "handle interrupt `n` with the routine at a label",
+    ),
+    (
+        ".entry",
+        ".entry label",
+        "start at a label, rather than at `start:` or the first instruction",
+    ),
+];
+
+/// each register's name with its [`Register::doc`]
+pub fn registers() -> Vec<(String, &'static str)> {
+    Register::ALL
+        .iter()
+        .map(|r| (format!("{r:?}").to_lowercase(), r.doc()))
+        .collect()
+}
+
+thread_local! {
+    /// whether the thread is in [`quietly`], for the hook [`quiet_panics`]
+    /// installs
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// catches a panic in the assembler, which the hook from [`quiet_panics`]
+/// does not print
+fn quietly<R>(f: impl FnOnce() -> R) -> thread::Result<R> {
+    QUIET.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    QUIET.set(false);
+    result
+}
+
+/// stops printing the panics caught while checking a document, as an
+/// invalid keystroke is not worth a message on stderr. any other panic
+/// still goes to the hook installed before
+pub fn quiet_panics() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !QUIET.get() {
+            hook(info);
+        }
+    }));
+}
+
+/// a span of one line, in zero based chars
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub line: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl From<Range> for Json {
+    fn from(value: Range) -> Self {
+        let position = |character: u32| {
+            Json::object([("line", value.line.into()), ("character", character.into())])
+        };
+        Json::object([
+            ("start", position(value.start)),
+            ("end", position(value.end)),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub range: Range,
+    /// an error stops the program assembling, anything else is a warning
+    pub error: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Keyword = 14,
+    Register = 6,
+    Label = 21,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: String,
+}
+
+/// what an identifier or directive is, from where it sits on its line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Label,
+    Mnemonic,
+    Directive,
+    Register,
+    /// the `sparse` in `lea`
+    Sparse,
+    Reference,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Symbol<'a> {
+    role: Role,
+    line: u32,
+    col: u32,
+    text: &'a str,
+}
+
+impl Symbol<'_> {
+    fn range(&self) -> Range {
+        Range {
+            line: self.line,
+            start: self.col,
+            end: self.col + self.text.chars().count() as u32,
+        }
+    }
+
+    /// whether the cursor is on the symbol or just after it
+    fn contains(&self, line: u32, col: u32) -> bool {
+        let range = self.range();
+        line == range.line && (range.start..=range.end).contains(&col)
+    }
+}
+
+fn symbols<'a>(lexemes: &[(Advance, &'a str)]) -> Vec<Symbol<'a>> {
+    let registers = registers();
+    let mut out = Vec::new();
+    for (i, &(ad, text)) in lexemes.iter().enumerate() {
+        let first = i == 0 || matches!(lexemes[i - 1].0.lex, Lexeme::Eol(_));
+        let role = match ad.lex {
+            Lexeme::Directive => Role::Directive,
+            Lexeme::Ident if first => match lexemes.get(i + 1) {
+                Some((next, _)) if next.lex == Lexeme::Colon => Role::Label,
+                _ => Role::Mnemonic,
+            },
+            Lexeme::Ident if registers.iter().any(|(name, _)| name == text) => Role::Register,
+            Lexeme::Ident if text == "sparse" => Role::Sparse,
+            Lexeme::Ident => Role::Reference,
+            _ => continue,
+        };
+        out.push(Symbol {
+            role,
+            line: ad.line,
+            col: ad.span.from - ad.offset,
+            text,
+        });
+    }
+    out
+}
+
+/// a lexer that remembers the line it last lexed, which is the line a parse
+/// error is raised on
+#[derive(Default)]
+struct Tracking<'a> {
+    base: BaseLexer<'a>,
+    line: Rc<Cell<u32>>,
+}
+
+impl Lexer for Tracking<'_> {
+    fn pop_peek(&mut self) -> Option<Advance> {
+        Lexer::pop_peek(&mut self.base)
+    }
+
+    fn peek(&mut self) -> Advance {
+        let ad = Lexer::peek(&mut self.base);
+        self.line.set(ad.line);
+        ad
+    }
+
+    fn advance(&mut self) -> Advance {
+        let ad = Lexer::advance(&mut self.base);
+        self.line.set(ad.line);
+        ad
+    }
+}
+
+/// the line a parse error belongs on. most are raised while their line is
+/// lexed, the rest name what they are about
+fn error_line(message: &str, symbols: &[Symbol], lexed: u32) -> u32 {
+    let find = |role: Role, text: &str, nth: usize| {
+        symbols
+            .iter()
+            .filter(|s| s.role == role && s.text == text)
+            .nth(nth)
+            .map(|s| s.line)
+    };
+    let line = if let Some(label) = message.strip_prefix("duplicate label: ") {
+        find(Role::Label, label, 1)
+    } else if message.starts_with("undefined entry label: ") {
+        find(Role::Directive, ".entry", 0)
+    } else if let Some((_, i)) = message.rsplit_once("from instruction ") {
+        let i = i.parse::<usize>().ok();
+        i.and_then(|i| symbols.iter().filter(|s| s.role == Role::Mnemonic).nth(i))
+            .map(|s| s.line)
+    } else {
+        None
+    };
+    line.unwrap_or(lexed)
+}
+
+/// errors and warnings for the source, parse errors stop at the first one
+pub fn diagnostics(src: &str) -> Vec<Diagnostic> {
+    let lexemes = lexemes(src);
+    let symbols = symbols(&lexemes);
+    // everything on the line but the comment
+    let whole_line = |line: u32| {
+        let mut on_line = lexemes
+            .iter()
+            .filter(|(ad, _)| ad.line == line && !matches!(ad.lex, Lexeme::Eol(_)))
+            .map(|(ad, text)| {
+                let start = ad.span.from - ad.offset;
+                (start, start + text.chars().count() as u32)
+            });
+        let first = on_line.next().unwrap_or_default();
+        let end = on_line.next_back().unwrap_or(first).1;
+        Range {
+            line,
+            start: first.0,
+            end,
+        }
+    };
+    let lexed = Rc::new(Cell::new(0));
+    let parser = Parser {
+        src,
+        lexer: Tracking {
+            base: BaseLexer::new(src),
+            line: lexed.clone(),
+        },
+        ..Default::default()
+    };
+    let error = |message: &str, line: u32| Diagnostic {
+        range: whole_line(line),
+        error: true,
+        message: message.to_owned(),
+    };
+    // errors in the assembler are panics
+    let program = match quietly(|| parser.program()) {
+        Ok(program) => program,
+        Err(err) => {
+            let message = panic_message(err.as_ref());
+            return vec![error(message, error_line(message, &symbols, lexed.get()))];
+        }
+    };
+    let defined = symbols
+        .iter()
+        .filter(|s| s.role == Role::Label)
+        .map(|s| s.text)
+        .collect::<Vec<_>>();
+    let undefined = symbols
+        .iter()
+        .filter(|s| s.role == Role::Reference && !defined.contains(&s.text))
+        .map(|s| Diagnostic {
+            range: s.range(),
+            error: true,
+            message: format!("undefined label: {}", s.text),
+        })
+        .collect::<Vec<_>>();
+    if !undefined.is_empty() {
+        return undefined;
+    }
+    let vm = match quietly(|| program.build()) {
+        Ok(vm) => vm,
+        Err(err) => return vec![error(panic_message(err.as_ref()), 0)],
+    };
+    verify::verify(&vm)
+        .into_iter()
+        .map(|issue| {
+            let line = vm.lines.get(issue.index).or(vm.lines.last());
+            Diagnostic {
+                range: whole_line(line.copied().unwrap_or_default()),
+                error: false,
+                message: verify::report(&vm, &[issue]).trim_end().to_owned(),
+            }
+        })
+        .collect()
+}
+
+/// the label defined or used at the cursor
+fn label_at<'a>(symbols: &[Symbol<'a>], line: u32, col: u32) -> Option<&'a str> {
+    symbols
+        .iter()
+        .find(|s| matches!(s.role, Role::Label | Role::Reference) && s.contains(line, col))
+        .map(|s| s.text)
+}
+
+/// where the label at the cursor is defined
+pub fn definition(src: &str, line: u32, col: u32) -> Option<Range> {
+    let symbols = symbols(&lexemes(src));
+    let label = label_at(&symbols, line, col)?;
+    symbols
+        .iter()
+        .find(|s| s.role == Role::Label && s.text == label)
+        .map(Symbol::range)
+}
+
+/// every use of the label at the cursor, and its definition if `declaration`
+pub fn references(src: &str, line: u32, col: u32, declaration: bool) -> Vec<Range> {
+    let symbols = symbols(&lexemes(src));
+    let Some(label) = label_at(&symbols, line, col) else {
+        return Vec::new();
+    };
+    symbols
+        .iter()
+        .filter(|s| s.text == label)
+        .filter(|s| s.role == Role::Reference || declaration && s.role == Role::Label)
+        .map(Symbol::range)
+        .collect()
+}
+
+/// markdown describing the symbol at the cursor
+pub fn hover(src: &str, line: u32, col: u32) -> Option<String> {
+    let symbols = symbols(&lexemes(src));
+    let symbol = symbols.iter().find(|s| s.contains(line, col))?;
+    let doc = |table: &[(&str, &str, &str)]| {
+        table
+            .iter()
+            .find(|(name, ..)| *name == symbol.text)
+            .map(|(_, syntax, doc)| format!("```\n{syntax}\n```\n{doc}"))
+    };
+    match symbol.role {
+        Role::Mnemonic => doc(MNEMONICS),
+        Role::Directive => doc(DIRECTIVES),
+        Role::Register => registers()
+            .into_iter()
+            .find(|(name, _)| name == symbol.text)
+            .map(|(name, doc)| format!("`{name}`: {doc}")),
+        Role::Sparse => {
+            Some("`sparse`: one byte per word rather than packed big-endian".to_owned())
+        }
+        Role::Label | Role::Reference => {
+            let def = symbols
+                .iter()
+                .find(|s| s.role == Role::Label && s.text == symbol.text)?;
+            let index = symbols
+                .iter()
+                .take_while(|s| s.line < def.line)
+                .filter(|s| s.role == Role::Mnemonic)
+                .count();
+            Some(format!(
+                "label `{}`, instruction {index} on line {}",
+                def.text,
+                def.line + 1
+            ))
+        }
+    }
+}
+
+/// mnemonics and directives at the start of a line, registers and labels
+/// after it
+pub fn completion(src: &str, line: u32, col: u32) -> Vec<Completion> {
+    let lexemes = lexemes(src);
+    let before = lexemes
+        .iter()
+        .filter(|(ad, _)| ad.line == line && ad.span.from - ad.offset < col)
+        .collect::<Vec<_>>();
+    if before.iter().any(|(ad, _)| ad.lex == Lexeme::Eol(true)) {
+        return Vec::new();
+    }
+    // nothing but the word being typed comes before the cursor
+    let first = match before[..] {
+        [] => true,
+        [(ad, text)] => ad.span.from - ad.offset + text.chars().count() as u32 >= col,
+        _ => false,
+    };
+    if first {
+        return MNEMONICS
+            .iter()
+            .chain(DIRECTIVES)
+            .map(|(name, syntax, _)| Completion {
+                label: name.to_string(),
+                kind: CompletionKind::Keyword,
+                detail: syntax.to_string(),
+            })
+            .collect();
+    }
+    let registers = registers().into_iter().map(|(label, detail)| Completion {
+        label,
+        kind: CompletionKind::Register,
+        detail: detail.to_owned(),
+    });
+    let labels = symbols(&lexemes)
+        .into_iter()
+        .filter(|s| s.role == Role::Label)
+        .map(|s| Completion {
+            label: s.text.to_owned(),
+            kind: CompletionKind::Label,
+            detail: format!("line {}", s.line + 1),
+        });
+    registers.chain(labels).collect()
+}
+
+/// serves one client until it sends `exit` or closes the input, returning
+/// whether it asked to shut down first
+pub fn serve(mut input: impl BufRead, output: impl Write) -> io::Result<bool> {
+    let mut server = Server {
+        out: output,
+        docs: AHashMap::new(),
+        shutdown: false,
+    };
+    while let Some(body) = read_message(&mut input)? {
+        let msg = match Json::parse(&body) {
+            Ok(msg) => msg,
+            Err(e) => {
+                server.send(response(Json::Null, Err((-32700, e))))?;
+                continue;
+            }
+        };
+        // responses to requests of our own, which we never send
+        let Some(method) = msg.get("method").and_then(Json::as_str) else {
+            continue;
+        };
+        let params = msg.get("params").unwrap_or(&Json::Null);
+        match msg.get("id") {
+            _ if method == "exit" => break,
+            Some(id) => {
+                let result = server.request(method, params);
+                server.send(response(id.clone(), result))?;
+            }
+            None => server.notify(method, params)?,
+        }
+    }
+    Ok(server.shutdown)
+}
+
+/// the body of the next message, none once the input closes
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            len = value.trim().parse().ok();
+        }
+    }
+    let Some(len) = len else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing content length",
+        ));
+    };
+    let mut body = vec![0; len];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn response(id: Json, result: Result<Json, (i32, String)>) -> Json {
+    let body = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            Json::object([
+                ("code", Json::Number(code as f64)),
+                ("message", message.into()),
+            ]),
+        ),
+    };
+    Json::object([("jsonrpc", "2.0".into()), ("id", id), body])
+}
+
+struct Server<W> {
+    out: W,
+    /// open documents by uri
+    docs: AHashMap<String, String>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, msg: Json) -> io::Result<()> {
+        let body = msg.to_string();
+        write!(self.out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.out.flush()
+    }
+
+    /// the result of a request, or an error code and message
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i32, String)> {
+        match method {
+            "initialize" => {
+                let capabilities = Json::object([
+                    // full text on every change
+                    ("textDocumentSync", 1u32.into()),
+                    ("definitionProvider", true.into()),
+                    ("referencesProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    (
+                        "completionProvider",
+                        Json::object([("triggerCharacters", vec![".".into()].into())]),
+                    ),
+                ]);
+                return Ok(Json::object([
+                    ("capabilities", capabilities),
+                    ("serverInfo", Json::object([("name", "hex-lsp".into())])),
+                ]));
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                return Ok(Json::Null);
+            }
+            "textDocument/definition"
+            | "textDocument/references"
+            | "textDocument/hover"
+            | "textDocument/completion" => (),
+            _ => return Err((-32601, format!("unknown method: {method}"))),
+        }
+        let invalid = |msg: &str| (-32602, msg.to_owned());
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid("expected a document"))?;
+        let src = self
+            .docs
+            .get(uri)
+            .ok_or_else(|| invalid("unknown document"))?;
+        let position = |key: &str| params.at(&["position", key]).and_then(Json::as_u32);
+        let (Some(line), Some(col)) = (position("line"), position("character")) else {
+            return Err(invalid("expected a position"));
+        };
+        let location = |range: Range| Json::object([("uri", uri.into()), ("range", range.into())]);
+        Ok(match method {
+            "textDocument/definition" => definition(src, line, col).map(location).into(),
+            "textDocument/references" => {
+                let declaration = params
+                    .at(&["context", "includeDeclaration"])
+                    .and_then(Json::as_bool)
+                    .unwrap_or(true);
+                let refs = references(src, line, col, declaration);
+                refs.into_iter().map(location).collect::<Vec<_>>().into()
+            }
+            "textDocument/hover" => hover(src, line, col)
+                .map(|value| {
+                    let contents =
+                        Json::object([("kind", "markdown".into()), ("value", value.into())]);
+                    Json::object([("contents", contents)])
+                })
+                .into(),
+            _ => completion(src, line, col)
+                .into_iter()
+                .map(|c| {
+                    Json::object([
+                        ("label", c.label.into()),
+                        ("kind", (c.kind as u32).into()),
+                        ("detail", c.detail.into()),
+                    ])
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        })
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let Some(uri) = params.at(&["textDocument", "uri"]).and_then(Json::as_str) else {
+            return Ok(());
+        };
+        let text = match method {
+            "textDocument/didOpen" => params.at(&["textDocument", "text"]),
+            // synced in full, so the last change holds the whole text
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                self.docs.remove(uri);
+                return self.publish(uri, Vec::new());
+            }
+            _ => return Ok(()),
+        };
+        let Some(text) = text.and_then(Json::as_str) else {
+            return Ok(());
+        };
+        self.docs.insert(uri.to_owned(), text.to_owned());
+        self.publish(uri, diagnostics(text))
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Diagnostic>) -> io::Result<()> {
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|d| {
+                let severity: u32 = if d.error { 1 } else { 2 };
+                Json::object([
+                    ("range", d.range.into()),
+                    ("severity", severity.into()),
+                    ("source", "hex-vm".into()),
+                    ("message", d.message.into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let params = Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]);
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", params),
+        ]))
+    }
+}
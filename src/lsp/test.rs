@@ -0,0 +1,184 @@
+use expect_test::expect;
+
+use crate::{json::Json, Register};
+
+fn diagnostics(src: &str) -> String {
+    super::diagnostics(src)
+        .iter()
+        .map(|d| {
+            let kind = if d.error { "error" } else { "warning" };
+            let r = d.range;
+            format!("{}:{}-{} {kind}: {}\n", r.line, r.start, r.end, d.message)
+        })
+        .collect()
+}
+
+#[test]
+fn parse_errors() {
+    let errors = [
+        "mov ax, 1\n  mov ax,, 2 ; two commas\nhlt",
+        "top:\n  inc ax\ntop:\n  jmp top",
+        "  jmp +1\n  jmp -5",
+        ".entry main\nmov ax, 1",
+        "  mov ax, 1\n  frob ax",
+    ]
+    .map(diagnostics)
+    .concat();
+    expect![[r#"
+        1:2-12 error: unexpected lexeme: Advance { lex: Comma, line: 1, offset: 10, span: (19, 20) }
+        2:0-4 error: duplicate label: top
+        1:2-8 error: relative jump out of range: -5 from instruction 1
+        0:0-11 error: undefined entry label: main
+        1:2-9 error: invalid instruction: frob
+    "#]]
+    .assert_eq(&errors);
+}
+
+#[test]
+fn labels_and_stack() {
+    let src = "
+start:
+    call work
+    jmp done
+work:
+    push ax
+    ret
+";
+    expect![[r#"
+        3:8-12 error: undefined label: done
+    "#]]
+    .assert_eq(&diagnostics(src));
+    expect![[r#"
+        6:4-7 warning: work at 3: ret with a stack depth of 1
+    "#]]
+    .assert_eq(&diagnostics(&src.replace("jmp done", "hlt")));
+    assert_eq!(
+        diagnostics(include_str!("../../project-euler/problem-1.asm")),
+        ""
+    );
+}
+
+const SRC: &str = "\
+.entry main
+loop:
+    dec cx
+    jne loop ; again
+main:
+    lea si, cx, sparse \"hi\"
+    jmp loop
+";
+
+#[test]
+fn navigation() {
+    let range = |r: super::Range| format!("{}:{}-{}", r.line, r.start, r.end);
+    assert_eq!(
+        super::definition(SRC, 3, 9).map(range).as_deref(),
+        Some("1:0-4")
+    );
+    assert_eq!(
+        super::definition(SRC, 0, 11).map(range).as_deref(),
+        Some("4:0-4")
+    );
+    assert_eq!(super::definition(SRC, 2, 5), None);
+    let refs = |declaration| {
+        super::references(SRC, 6, 8, declaration)
+            .into_iter()
+            .map(range)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(refs(false), ["3:8-12", "6:8-12"]);
+    assert_eq!(refs(true), ["1:0-4", "3:8-12", "6:8-12"]);
+
+    let hovers = [(2, 5), (2, 9), (0, 3), (5, 18), (6, 10), (3, 14)]
+        .map(|(line, col)| super::hover(SRC, line, col).unwrap_or_default() + "\n")
+        .concat();
+    expect![[r#"
+        ```
+        dec dst
+        ```
+        subtract one from a register or memory
+        `cx`: counter, volatile, arg 4
+        ```
+        .entry label
+        ```
+        start at a label, rather than at `start:` or the first instruction
+        `sparse`: one byte per word rather than packed big-endian
+        label `loop`, instruction 0 on line 2
+
+    "#]]
+    .assert_eq(&hovers);
+}
+
+#[test]
+fn registers() {
+    let registers = super::registers();
+    let names = Register::ALL.map(|r| format!("{r:?}").to_lowercase());
+    assert!(registers.iter().map(|(name, _)| name).eq(&names));
+    assert!(registers.iter().all(|(_, doc)| !doc.is_empty()));
+}
+
+#[test]
+fn completion() {
+    let labels = |line, col| {
+        super::completion(SRC, line, col)
+            .into_iter()
+            .map(|c| c.label)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    expect!["mov cmp jmp je jne jl jle jg jge call ret push pop add sub inc dec mul div mod str sparse lea print int iret hlt exit .vector .entry"]
+        .assert_eq(&labels(2, 6));
+    assert_eq!(labels(2, 6), labels(7, 0));
+    expect!["ax bx cx dx si di sp bp ip loop main"].assert_eq(&labels(6, 8));
+    assert_eq!(labels(3, 15), "");
+}
+
+fn frame(msg: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{msg}", msg.len())
+}
+
+#[test]
+fn session() {
+    let uri = "file:///a.asm";
+    let open = Json::object([(
+        "textDocument",
+        Json::object([("uri", uri.into()), ("text", SRC.into())]),
+    )]);
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_owned(),
+        format!(r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{open}}}"#),
+        format!(
+            r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":6,"character":9}}}}}}"#
+        ),
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":"{uri}"}},"contentChanges":[{{"text":"jmp nowhere"}}]}}}}"#
+        ),
+        r#"{"jsonrpc":"2.0","id":3,"method":"workspace/symbol","params":{}}"#.to_owned(),
+        "{oops".to_owned(),
+        r#"{"jsonrpc":"2.0","id":4,"method":"shutdown"}"#.to_owned(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_owned(),
+    ]
+    .map(|msg| frame(&msg))
+    .concat();
+    let mut out = Vec::new();
+    assert!(super::serve(input.as_bytes(), &mut out).unwrap());
+    let out = String::from_utf8(out).unwrap();
+    let mut messages = String::new();
+    let mut rest = out.as_str();
+    while let Some((header, body)) = rest.split_once("\r\n\r\n") {
+        let len = header["Content-Length: ".len()..].parse::<usize>().unwrap();
+        messages += &body[..len];
+        messages += "\n";
+        rest = &body[len..];
+    }
+    expect![[r#"
+        {"jsonrpc":"2.0","id":1,"result":{"capabilities":{"textDocumentSync":1,"definitionProvider":true,"referencesProvider":true,"hoverProvider":true,"completionProvider":{"triggerCharacters":["."]}},"serverInfo":{"name":"hex-lsp"}}}
+        {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.asm","diagnostics":[]}}
+        {"jsonrpc":"2.0","id":2,"result":{"uri":"file:///a.asm","range":{"start":{"line":1,"character":0},"end":{"line":1,"character":4}}}}
+        {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.asm","diagnostics":[{"range":{"start":{"line":0,"character":4},"end":{"line":0,"character":11}},"severity":1,"source":"hex-vm","message":"undefined label: nowhere"}]}}
+        {"jsonrpc":"2.0","id":3,"error":{"code":-32601,"message":"unknown method: workspace/symbol"}}
+        {"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"expected a string at 1"}}
+        {"jsonrpc":"2.0","id":4,"result":null}
+    "#]]
+    .assert_eq(&messages);
+}
@@ -1,36 +1,227 @@
-#[allow(unused)]
-use hex_vm::{mem, reg, Register::*, Sequence::*, Value::*};
+use std::{fs::File, io::BufWriter};
+
+use hex_vm::{
+    cfg::Cfg, coverage::Coverage, parse::Parser, profile::Profiler, trace::JsonTracer,
+    trace::Tracer, watch::Stop, HexSize, HexVm, MEM_SIZE,
+};
+
+/// a tracer to run the program with
+enum Tool {
+    Trace(String),
+    Profile,
+    Coverage(String),
+}
 
-// TODO: implement call
 fn main() {
-    setup_tracing();
-    let m = 32;
-    let n = 1;
-    for i in n..m {
-        for j in i + 1..m {
-            let a = j * j - i * i;
-            let b = i * j * 2;
-            let c = j * j + i * i;
-            if a + b + c == 1000 {
-                println!("{a}² + {b}² = {c}²");
-                break;
-            }
-        }
-    }
-    let mut vm = hex_vm::parse::Parser::new(include_str!("../project-euler/problem-9.asm")).parse();
-    vm.run();
+    let mut path = None;
+    let mut tool = None;
+    let mut bytecode = false;
+    let mut opt = false;
+    let mut cfg = None;
+    let mut verify = false;
+    let mut debug = false;
+    let mut protect = false;
+    let mut golden = false;
+    let mut fmt = false;
+    let mut check = false;
+    let mut mem = MEM_SIZE;
+    let mut fuel = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let next = match arg.as_str() {
+            "--trace" => Tool::Trace(args.next().expect("expected a trace file")),
+            "--profile" => Tool::Profile,
+            "--coverage" => Tool::Coverage(args.next().expect("expected an lcov file")),
+            "--bytecode" => {
+                bytecode = true;
+                continue;
+            }
+            "--opt" => {
+                opt = true;
+                continue;
+            }
+            "--verify" => {
+                verify = true;
+                continue;
+            }
+            "--debug" => {
+                debug = true;
+                continue;
+            }
+            "--golden" => {
+                golden = true;
+                continue;
+            }
+            "--fmt" => {
+                fmt = true;
+                continue;
+            }
+            "--check" => {
+                (fmt, check) = (true, true);
+                continue;
+            }
+            "--protect" => {
+                protect = true;
+                continue;
+            }
+            "--mem" => {
+                mem = args
+                    .next()
+                    .and_then(|words| words.parse().ok())
+                    .expect("expected a memory size in words");
+                continue;
+            }
+            "--fuel" => {
+                fuel = args.next().and_then(|cycles| cycles.parse().ok());
+                if fuel.is_none() {
+                    panic!("expected a cycle count")
+                }
+                continue;
+            }
+            "--cfg" => {
+                cfg = Some(args.next().expect("expected a dot file"));
+                continue;
+            }
+            _ => {
+                path = Some(arg);
+                continue;
+            }
+        };
+        if tool.replace(next).is_some() {
+            panic!("only one of --trace, --profile and --coverage can be used at once");
+        }
+    }
+    if golden {
+        // the path is a directory of programs here
+        let dir = path.as_deref().unwrap_or("project-euler");
+        let outcomes = hex_vm::golden::check_dir(dir).expect("unable to read programs");
+        print!("{}", hex_vm::golden::table(&outcomes));
+        std::process::exit(!outcomes.iter().all(|o| o.passed()) as i32);
+    }
+    if fmt {
+        // a file or a directory of programs
+        let path = path.as_deref().unwrap_or("project-euler");
+        let changed = hex_vm::format::format_path(path, check).expect("unable to format programs");
+        for path in &changed {
+            match check {
+                true => eprintln!("would reformat {}", path.display()),
+                false => eprintln!("formatted {}", path.display()),
+            }
+        }
+        std::process::exit((check && !changed.is_empty()) as i32);
+    }
+    let (path, src) = match path {
+        Some(path) => {
+            let src = std::fs::read_to_string(&path).expect("unable to read source");
+            (path, src)
+        }
+        None => (
+            "project-euler/problem-9.asm".to_owned(),
+            include_str!("../project-euler/problem-9.asm").to_owned(),
+        ),
+    };
+    let mut vm = Parser::new(&src).parse_with_memory(mem);
+    if verify {
+        let issues = hex_vm::verify::verify(&vm);
+        eprint!("{}", hex_vm::verify::report(&vm, &issues));
+        std::process::exit(!issues.is_empty() as i32);
+    }
+    if let Some(dot) = cfg {
+        let cfg = Cfg::new(&vm);
+        std::fs::write(dot, cfg.to_dot(&vm)).expect("unable to write dot file");
+        eprint!("{}", cfg.report(&vm));
+        return;
+    }
+    if opt {
+        eprint!("{}", hex_vm::opt::optimize(&mut vm));
+    }
+    vm.fuel = fuel;
+    if protect {
+        vm.protect(hex_vm::protect::DEFAULT_STACK);
+    }
+    if debug {
+        if tool.is_some() || bytecode {
+            panic!("--debug runs on its own");
+        }
+        let stdin = std::io::stdin();
+        hex_vm::debug::repl(&mut vm, &src, stdin.lock(), std::io::stdout())
+            .expect("unable to run debugger");
+        report(&vm);
+        std::process::exit(vm.exit.map_or(0, exit_status));
+    }
+    let status = match tool {
+        Some(_) if bytecode => panic!("the bytecode engine does not trace"),
+        Some(Tool::Trace(trace)) => {
+            let out = BufWriter::new(File::create(trace).expect("unable to create trace"));
+            let mut vm = vm.with_tracer(JsonTracer::new(out));
+            let status = run(&mut vm);
+            report(&vm);
+            vm.tracer.finish().expect("unable to write trace");
+            status
+        }
+        Some(Tool::Profile) => {
+            let len = vm.seq.len();
+            let mut vm = vm.with_tracer(Profiler::new(len));
+            let status = run(&mut vm);
+            report(&vm);
+            eprint!("{}", vm.tracer.report(&vm, &src));
+            status
+        }
+        Some(Tool::Coverage(lcov)) => {
+            let len = vm.seq.len();
+            let mut vm = vm.with_tracer(Coverage::new(len));
+            let status = run(&mut vm);
+            report(&vm);
+            eprint!("{}", vm.tracer.listing(&vm, &src));
+            std::fs::write(lcov, vm.tracer.lcov(&vm, &path)).expect("unable to write lcov file");
+            status
+        }
+        None => {
+            let status = match bytecode {
+                true => {
+                    if let Err(unsupported) = hex_vm::bytecode::run(&mut vm) {
+                        eprintln!("{unsupported}");
+                        std::process::exit(1);
+                    }
+                    vm.exit.map_or(0, exit_status)
+                }
+                false => run(&mut vm),
+            };
+            report(&vm);
+            status
+        }
+    };
+    std::process::exit(status);
+}
+
+/// runs to a stop, returning the exit status and reporting any stop other
+/// than the end or an exit, which fail with 1
+fn run<T: Tracer>(vm: &mut HexVm<T>) -> i32 {
+    match vm.run() {
+        Stop::End => 0,
+        Stop::Exit(status) => exit_status(status),
+        stop => {
+            eprintln!("{stop}");
+            1
+        }
+    }
+}
+
+/// the os keeps only the low 8 bits of a status, so any other failure
+/// exits with 1 rather than passing for a success
+fn exit_status(status: HexSize) -> i32 {
+    match status {
+        0..=255 => status as i32,
+        _ => 1,
+    }
+}
+
+fn report<T: Tracer>(vm: &HexVm<T>) {
     println!(
-        "{:#?} {:#?} {:?}",
+        "{:#?} {:#?} {:?}\ncycles: {}",
         vm.reg,
         vm.flg,
-        &vm.mem[vm.reg.sp as usize..]
+        &vm.mem[vm.reg.sp as usize..],
+        vm.cycles
     );
 }
-
-fn setup_tracing() {
-    use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
-}
@@ -0,0 +1,275 @@
+//! peephole optimizations over a linked [`Sequence`]
+//!
+//! every rewrite keeps the observable registers intact, but it may leave
+//! different garbage below `sp` and different carry and overflow flags.
+
+use std::fmt;
+
+use crate::{trace::Tracer, Address, HexSize, HexVm, Register, Sequence, Value};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rewrite {
+    /// `jmp a` where `a: jmp b` becomes `jmp b`
+    JumpThread { from: HexSize, to: HexSize },
+    /// `mov ax, x` then `mul ax` becomes `mov ax, x` then `mul x`
+    ForwardMul,
+    /// `push r` then `pop r` is removed
+    PushPop,
+    /// `mov r, 0` then `add r, x` becomes `mov r, x`
+    MovAdd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    /// index of the first rewritten instruction before optimizing
+    pub index: usize,
+    pub rewrite: Rewrite,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub changes: Vec<Change>,
+    /// instructions removed
+    pub removed: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} rewrites, {} instructions removed",
+            self.changes.len(),
+            self.removed
+        )?;
+        for change in &self.changes {
+            write!(f, "{:>6}: ", change.index)?;
+            match change.rewrite {
+                Rewrite::JumpThread { from, to } => {
+                    writeln!(f, "threaded jump to {from} through to {to}")
+                }
+                Rewrite::ForwardMul => writeln!(f, "forwarded mov into mul"),
+                Rewrite::PushPop => writeln!(f, "removed push/pop pair"),
+                Rewrite::MovAdd => writeln!(f, "folded mov 0/add into mov"),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+/// links the vm's sequence and rewrites it in place
+pub fn optimize<T: Tracer>(vm: &mut HexVm<T>) -> Report {
+    vm.link();
+    let mut report = Report::default();
+    let leaders = leaders(vm);
+    thread_jumps(&mut vm.seq, &mut report);
+    forward_mul(&mut vm.seq, &leaders, &mut report);
+    // removing instructions renumbers them, which is only safe while no
+    // instruction index can end up in a register or in memory
+    if !indices_escape(&vm.seq) {
+        let keep = fold(&mut vm.seq, &leaders, &mut report);
+        renumber(vm, &keep);
+        report.removed = keep.iter().filter(|k| !**k).count();
+    }
+    report.changes.sort_by_key(|c| c.index);
+    report
+}
+
+/// every index that can be reached other than by falling through
+fn leaders<T: Tracer>(vm: &HexVm<T>) -> Vec<bool> {
+    let mut leaders = vec![false; vm.seq.len() + 1];
+    leaders[0] = true;
+    if let Some(l) = leaders.get_mut(vm.entry as usize) {
+        *l = true;
+    }
+    let targets = vm.seq.iter().filter_map(|seq| match seq.target() {
+        Some(Value::Hex(t)) => Some(t),
+        _ => None,
+    });
+    for t in vm.labels.values().copied().chain(targets) {
+        if let Some(l) = leaders.get_mut(t as usize) {
+            *l = true;
+        }
+    }
+    leaders
+}
+
+fn thread_jumps(seq: &mut [Sequence], report: &mut Report) {
+    for i in 0..seq.len() {
+        if !seq[i].is_jump() {
+            continue;
+        }
+        let Some(Value::Hex(from)) = seq[i].target() else {
+            continue;
+        };
+        let mut to = from;
+        // bounded, as jumps may form a cycle
+        for _ in 0..seq.len() {
+            match seq.get(to as usize) {
+                // a jump to itself falls through
+                Some(&Sequence::Jmp(Value::Hex(next))) if next != to => to = next,
+                _ => break,
+            }
+        }
+        if to != from && to != i as HexSize {
+            *seq[i].target_mut().unwrap() = Value::Hex(to);
+            report.changes.push(Change {
+                index: i,
+                rewrite: Rewrite::JumpThread { from, to },
+            });
+        }
+    }
+}
+
+fn forward_mul(seq: &mut [Sequence], leaders: &[bool], report: &mut Report) {
+    use Sequence::*;
+    for i in 1..seq.len() {
+        let (Mov(Address::Register(Register::Ax, false), x), Mul(ax)) = (seq[i - 1], seq[i]) else {
+            continue;
+        };
+        if leaders[i] || ax != Register::Ax.into() {
+            continue;
+        }
+        let forward = match x {
+            Value::Hex(_) => true,
+            Value::Address(Address::Register(r, false)) => {
+                !matches!(r, Register::Ax | Register::Ip)
+            }
+            _ => false,
+        };
+        if forward {
+            seq[i] = Mul(x);
+            report.changes.push(Change {
+                index: i - 1,
+                rewrite: Rewrite::ForwardMul,
+            });
+        }
+    }
+}
+
+/// rewrites the pairs that shrink, returning which instructions are kept
+fn fold(seq: &mut [Sequence], leaders: &[bool], report: &mut Report) -> Vec<bool> {
+    use Sequence::*;
+    let mut keep = vec![true; seq.len()];
+    let mut i = 1;
+    while i < seq.len() {
+        let rewrite = match (seq[i - 1], seq[i]) {
+            _ if leaders[i] => None,
+            (Push(Value::Address(a)), Pop(b))
+                if a == b && matches!(a, Address::Register(r, false) if r != Register::Sp) =>
+            {
+                keep[i - 1] = false;
+                keep[i] = false;
+                Some(Rewrite::PushPop)
+            }
+            (Mov(a @ Address::Register(r, false), Value::Hex(0)), Add(b, x))
+                if a == b && !reads(x, r) && flags_dead(seq, i + 1) =>
+            {
+                seq[i - 1] = Mov(a, x);
+                keep[i] = false;
+                Some(Rewrite::MovAdd)
+            }
+            _ => None,
+        };
+        if let Some(rewrite) = rewrite {
+            report.changes.push(Change {
+                index: i - 1,
+                rewrite,
+            });
+            i += 1;
+        }
+        i += 1;
+    }
+    keep
+}
+
+fn reads(value: Value, r: Register) -> bool {
+    matches!(value, Value::Address(Address::Register(x, _)) if x == r)
+}
+
+/// whether the flags set at `from - 1` are overwritten before being read,
+/// looking along the straight line only
+fn flags_dead(seq: &[Sequence], from: usize) -> bool {
+    use Sequence::*;
+    for seq in &seq[from.min(seq.len())..] {
+        match seq {
+            Cmp(..) | Add(..) | Sub(..) | Inc(_) | Dec(_) => return true,
+            s if s.target().is_some() || matches!(s, Ret | Int(_) | Iret | Hlt | Exit(_)) => {
+                return false
+            }
+            _ => (),
+        }
+    }
+    false
+}
+
+/// whether an instruction index can be observed other than through a
+/// linked target
+fn indices_escape(seq: &[Sequence]) -> bool {
+    use Sequence::*;
+    let escapes = |value: Value| match value {
+        Value::Address(add) => address_escapes(add),
+        _ => false,
+    };
+    seq.iter().any(|seq| match *seq {
+        Jmp(v) | Je(v) | Jne(v) | Jl(v) | Jle(v) | Jg(v) | Jge(v) | Call(v) => {
+            !matches!(v, Value::Hex(_))
+        }
+        Mov(a, v) | Add(a, v) | Sub(a, v) => address_escapes(a) || escapes(v),
+        Cmp(a, b) => escapes(a) || escapes(b),
+        Push(v) | Mul(v) | Div(v) | Mod(v) | Int(v) | Exit(v) => escapes(v),
+        Pop(a) | Inc(a) | Dec(a) | Print(a, _) => address_escapes(a),
+        Lea(a, b, _) => address_escapes(a) || address_escapes(b),
+        Ret | Iret | Hlt | Str(_) | Sparse(_) => false,
+    })
+}
+
+fn address_escapes(add: Address) -> bool {
+    matches!(add, Address::Register(Register::Ip, _) | Address::Ident(_))
+}
+
+/// drops removed instructions, moving every target and label onto the next
+/// kept instruction
+fn renumber<T: Tracer>(vm: &mut HexVm<T>, keep: &[bool]) {
+    let mut index = Vec::with_capacity(keep.len() + 1);
+    let mut kept = 0;
+    for &k in keep {
+        index.push(kept);
+        kept += k as HexSize;
+    }
+    index.push(kept);
+    let len = keep.len() as HexSize;
+    // anything past the end still ends the program
+    let map = |t: HexSize| {
+        if t < len {
+            index[t as usize]
+        } else {
+            t - (len - kept)
+        }
+    };
+    let mut i = 0;
+    vm.seq.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+    // each instruction keeps its source line
+    let mut i = 0;
+    vm.lines.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+    for seq in &mut vm.seq {
+        if let Some(Value::Hex(t)) = seq.target_mut() {
+            *t = map(*t);
+        }
+    }
+    for label in vm.labels.values_mut() {
+        *label = map(*label);
+    }
+    vm.entry = map(vm.entry);
+    vm.reg.ip = map(vm.reg.ip);
+    // the vector table holds instruction indices too
+    vm.load_statics();
+}
@@ -0,0 +1,164 @@
+use expect_test::expect;
+
+use crate::{parse::Parser, profile::Profiler, HexSize, Sequence};
+
+#[test]
+fn rewrites() {
+    let mut vm = Parser::new(
+        "
+                mov cx, 3
+            top:
+                push bx
+                pop bx
+                mov ax, cx
+                mul ax
+                mov dx, 0
+                add dx, ax
+                dec cx
+                jne skip
+                jmp end
+            skip:
+                jmp top
+            end:
+            ",
+    )
+    .parse();
+    let report = super::optimize(&mut vm);
+    expect![[r#"
+        4 rewrites, 3 instructions removed
+             1: removed push/pop pair
+             3: forwarded mov into mul
+             5: folded mov 0/add into mov
+             8: threaded jump to 10 through to 1
+    "#]]
+    .assert_eq(&report.to_string());
+    let seq = vm
+        .seq
+        .iter()
+        .map(|s| format!("{s:?}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    expect![[r#"
+        Mov(Register(Cx, false), Hex(3))
+        Mov(Register(Ax, false), Address(Register(Cx, false)))
+        Mul(Address(Register(Cx, false)))
+        Mov(Register(Dx, false), Address(Register(Ax, false)))
+        Dec(Register(Cx, false))
+        Jne(Hex(1))
+        Jmp(Hex(8))
+        Jmp(Hex(1))"#]]
+    .assert_eq(&seq);
+}
+
+#[test]
+fn forward_mul() {
+    // a jump into the `mul`, or a source that is not a plain register or
+    // number, keeps `mul ax`
+    let mut vm = Parser::new(
+        "
+                mov ax, cx
+                mul ax
+                mov ax, 7
+                mul ax
+                mov ax, [bx]
+                mul ax
+                mov ax, si
+            again:
+                mul ax
+                jmp again
+            ",
+    )
+    .parse();
+    let report = super::optimize(&mut vm);
+    expect![[r#"
+        2 rewrites, 0 instructions removed
+             0: forwarded mov into mul
+             2: forwarded mov into mul
+    "#]]
+    .assert_eq(&report.to_string());
+    let muls = vm
+        .seq
+        .iter()
+        .filter(|s| matches!(s, Sequence::Mul(_)))
+        .map(|s| format!("{s:?}\n"))
+        .collect::<String>();
+    expect![[r#"
+        Mul(Address(Register(Cx, false)))
+        Mul(Hex(7))
+        Mul(Address(Register(Ax, false)))
+        Mul(Address(Register(Ax, false)))
+    "#]]
+    .assert_eq(&muls);
+}
+
+/// runs `src` with and without optimizing, which must end the same
+fn check(src: &str) {
+    let mut plain = Parser::new(src).parse();
+    plain.run();
+    let mut opt = Parser::new(src).parse();
+    super::optimize(&mut opt);
+    opt.run();
+    // programs leave through a jump past the end, which is renumbered too
+    let removed = (plain.seq.len() - opt.seq.len()) as HexSize;
+    assert!(plain.reg.ip >= plain.seq.len() as HexSize);
+    plain.reg.ip -= removed;
+    assert_eq!(plain.reg, opt.reg);
+    assert!(opt.cycles <= plain.cycles);
+}
+
+#[test]
+fn euler() {
+    for src in [
+        include_str!("../../project-euler/problem-1.asm"),
+        include_str!("../../project-euler/problem-2.asm"),
+        include_str!("../../project-euler/problem-3.asm"),
+        include_str!("../../project-euler/problem-4.asm"),
+        include_str!("../../project-euler/problem-6.asm"),
+        include_str!("../../project-euler/problem-7.asm"),
+        include_str!("../../project-euler/problem-8.asm"),
+        include_str!("../../project-euler/problem-9.asm"),
+    ] {
+        check(src);
+    }
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore = "too slow without optimizations")]
+fn euler_slow() {
+    check(include_str!("../../project-euler/problem-5.asm"));
+}
+
+#[test]
+fn profiled() {
+    let src = "
+        push bx
+        pop bx
+        mov cx, 2
+    loop:
+        dec cx
+        jne loop
+    ";
+    let mut vm = Parser::new(src).parse();
+    super::optimize(&mut vm);
+    assert_eq!(vm.lines, [3, 5, 6]);
+    let len = vm.seq.len();
+    let mut vm = vm.with_tracer(Profiler::new(len));
+    vm.run();
+    let out = vm
+        .tracer
+        .report(&vm, src)
+        .lines()
+        .map(|l| l.trim_start().to_owned() + "\n")
+        .collect::<String>();
+    expect![[r#"
+        count  index   line  source
+        2      1      6  dec cx
+        2      2      7  jne loop
+        1      0      4  mov cx, 2
+
+        total  label
+        4  loop
+        1  <entry>
+    "#]]
+    .assert_eq(&out);
+}
@@ -2,10 +2,12 @@ use ahash::AHashMap;
 use string_interner::{DefaultStringInterner, DefaultSymbol};
 
 use crate::{
+    build::{HexVmBuilder, Program},
+    interrupt::VECTORS,
     lex::{Advance, BaseLexer, Lexeme::*, Lexer},
     mem, reg,
     span::Span,
-    Address, HexSize, HexVm, Register, Sequence, Value,
+    Address, HexSize, HexVm, IHexSize, Register, Sequence, Static, Value,
 };
 
 #[cfg(test)]
@@ -17,7 +19,12 @@ pub struct Parser<L, S> {
     pub src: S,
     pub lexer: L,
     pub seq: Vec<Sequence>,
+    pub lines: Vec<u32>,
     pub labels: AHashMap<DefaultSymbol, HexSize>,
+    pub statics: Vec<Static>,
+    pub vectors: Vec<Option<DefaultSymbol>>,
+    /// the label set with `.entry`
+    pub entry: Option<DefaultSymbol>,
 }
 
 impl<'a> Parser<BaseLexer<'a>, &'a str> {
@@ -31,11 +38,42 @@ impl<'a> Parser<BaseLexer<'a>, &'a str> {
 }
 
 impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
-    pub fn parse(mut self) -> HexVm {
+    pub fn parse(self) -> HexVm {
+        self.program().build()
+    }
+
+    /// parses into a vm with `words` of memory
+    pub fn parse_with_memory(self, words: usize) -> HexVm {
+        HexVmBuilder::new(self.program()).memory(words).build()
+    }
+
+    /// assembles the source without building a vm
+    pub fn program(mut self) -> Program {
         self.parse_inner();
-        HexVm {
+        self.check_relative();
+        let entry = match self.entry {
+            Some(sym) => match self.labels.get(&sym) {
+                Some(&entry) => entry,
+                None => panic!(
+                    "undefined entry label: {}",
+                    self.si.resolve(sym).unwrap_or("<unknown>")
+                ),
+            },
+            // the convention the euler problems follow
+            None => self
+                .si
+                .get("start")
+                .and_then(|sym| self.labels.get(&sym).copied())
+                .unwrap_or(0),
+        };
+        Program {
             si: self.si,
-            ..HexVm::new(self.seq, self.labels)
+            labels: self.labels,
+            statics: self.statics,
+            seq: self.seq,
+            lines: self.lines,
+            vectors: self.vectors,
+            entry,
         }
     }
 
@@ -48,8 +86,10 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
                     let Some(value) = self.parse_line(ad) else {
                         continue;
                     };
-                    self.seq.push(value)
+                    self.seq.push(value);
+                    self.lines.push(ad.line);
                 }
+                Directive => self.directive(ad),
                 Eol(_) => (),
                 Eof => break,
                 _ => self.unexpected(ad),
@@ -57,6 +97,62 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
         }
     }
 
+    fn directive(&mut self, ad: Advance) {
+        match self.slice(ad.span) {
+            ".vector" => {
+                let n = self.expect_hex();
+                if n >= VECTORS as HexSize {
+                    self.kill_line();
+                    panic!("interrupt number out of range: {n}")
+                }
+                self.expect_comma();
+                let label = self.non_ws();
+                let Ident = label.lex else {
+                    self.unexpected(label);
+                };
+                self.clear_line();
+                let sym = self.symbol(label.span);
+                let n = n as usize;
+                if self.vectors.len() <= n {
+                    self.vectors.resize(n + 1, None);
+                }
+                if self.vectors[n].replace(sym).is_some() {
+                    panic!("duplicate vector: {n}")
+                }
+            }
+            ".entry" => {
+                let label = self.non_ws();
+                let Ident = label.lex else {
+                    self.unexpected(label);
+                };
+                self.clear_line();
+                let sym = self.symbol(label.span);
+                if self.entry.replace(sym).is_some() {
+                    panic!("duplicate entry")
+                }
+            }
+            s => {
+                let s = s.to_owned();
+                self.kill_line();
+                panic!("invalid directive: {s}")
+            }
+        }
+    }
+
+    /// ensures every relative jump lands inside the program, where
+    /// `seq.len()` is allowed as it ends execution
+    fn check_relative(&self) {
+        for (i, seq) in self.seq.iter().enumerate() {
+            let Some(Value::IHex(diff)) = seq.jump_value() else {
+                continue;
+            };
+            let target = (i as IHexSize).checked_add(diff);
+            if !target.is_some_and(|t| (0..=self.seq.len() as IHexSize).contains(&t)) {
+                panic!("relative jump out of range: {diff:+} from instruction {i}")
+            }
+        }
+    }
+
     fn parse_line(&mut self, first: Advance) -> Option<Sequence> {
         let second = self.peek_non_ws();
         if let Colon = second.lex {
@@ -89,12 +185,24 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
                     self.unexpected(ad);
                 };
                 self.kill_line();
-                Some(Sequence::Call(self.symbol(ad.span)))
+                Some(Sequence::Call(Value::Address(Address::Ident(
+                    self.symbol(ad.span),
+                ))))
             }
             "ret" => {
                 self.kill_line();
                 Some(Sequence::Ret)
             }
+            "int" => Some(Sequence::Int(self.expect_value())),
+            "hlt" => {
+                self.kill_line();
+                Some(Sequence::Hlt)
+            }
+            "exit" => Some(Sequence::Exit(self.expect_value())),
+            "iret" => {
+                self.kill_line();
+                Some(Sequence::Iret)
+            }
             "mul" => Some(Sequence::Mul(self.expect_value())),
             "div" => Some(Sequence::Div(self.expect_value())),
             "mod" => Some(Sequence::Mod(self.expect_value())),
@@ -102,24 +210,27 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
             "inc" => Some(Sequence::Inc(self.expect_address())),
             "dec" => Some(Sequence::Dec(self.expect_address())),
             "str" => {
-                let ad = self.non_ws();
-                let Str = ad.lex else {
-                    self.unexpected(ad);
-                };
-                self.kill_line();
-                Some(Sequence::Str(
-                    self.symbol((ad.span.from + 1, ad.span.to - 1)),
-                ))
+                let sym = self.expect_str();
+                Some(Sequence::Str(self.static_str(sym, false)))
             }
             "sparse" => {
-                let ad = self.non_ws();
-                let Str = ad.lex else {
-                    self.unexpected(ad);
+                let sym = self.expect_str();
+                Some(Sequence::Sparse(self.static_str(sym, true)))
+            }
+            "lea" => {
+                let address = self.expect_address();
+                self.expect_comma();
+                let len = self.expect_address();
+                self.expect_comma();
+                let sparse = {
+                    let ad = self.peek_non_ws();
+                    ad.lex == Ident && self.slice(ad.span) == "sparse"
                 };
-                self.kill_line();
-                Some(Sequence::Sparse(
-                    self.symbol((ad.span.from + 1, ad.span.to - 1)),
-                ))
+                if sparse {
+                    self.lexer.pop_peek();
+                }
+                let sym = self.expect_str();
+                Some(Sequence::Lea(address, len, self.static_str(sym, sparse)))
             }
             "mov" => {
                 let address = self.expect_address();
@@ -182,6 +293,35 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
         }
     }
 
+    fn expect_str(&mut self) -> DefaultSymbol {
+        let ad = self.non_ws();
+        let Str = ad.lex else {
+            self.unexpected(ad);
+        };
+        self.kill_line();
+        self.symbol((ad.span.from + 1, ad.span.to - 1))
+    }
+
+    /// places a string in the static area, reusing an identical one
+    fn static_str(&mut self, sym: DefaultSymbol, sparse: bool) -> usize {
+        if let Some(i) = self
+            .statics
+            .iter()
+            .position(|st| st.sym == sym && st.sparse == sparse)
+        {
+            return i;
+        }
+        let len = self.si.resolve(sym).expect("invalid symbol").len() as HexSize;
+        let addr = self.statics.last().map_or(0, Static::end);
+        self.statics.push(Static {
+            sym,
+            sparse,
+            addr,
+            len,
+        });
+        self.statics.len() - 1
+    }
+
     fn expect_comma(&mut self) {
         let ad = self.non_ws();
         if Comma != ad.lex {
@@ -198,11 +338,17 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
                 Err(s) => Some(Value::Address(Address::Ident(s))),
             },
             Ident => Some(Value::Address(reg!(self.reg(ad.span)))),
-            Digit(base) => {
-                let n = HexSize::from_str_radix(self.slice(ad.span), base as u32)
-                    .expect("invalid integer");
-                Some(Value::Hex(n))
+            Digit(base) => Some(Value::Hex(self.digit(ad.span, base as u32))),
+            Plus | Minus => {
+                let digit = self.lexer.advance();
+                let Digit(base) = digit.lex else {
+                    self.unexpected(digit);
+                };
+                let n = IHexSize::try_from(self.digit(digit.span, base as u32))
+                    .expect("integer out of range");
+                Some(Value::IHex(if ad.lex == Minus { -n } else { n }))
             }
+            Char => Some(Value::Hex(self.char_literal(ad.span) as HexSize)),
             OpenBracket => {
                 let (span, db) = self.after_bracket();
                 db.map_or_else(
@@ -217,9 +363,7 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
     fn after_bracket(&mut self) -> (Span, Option<HexSize>) {
         let first = self.non_ws();
         let db = match first.lex {
-            Digit(b) => Some(
-                HexSize::from_str_radix(self.slice(first.span), b as u32).expect("invalid integer"),
-            ),
+            Digit(b) => Some(self.digit(first.span, b as u32)),
             Ident => None,
             _ => self.unexpected(first),
         };
@@ -233,6 +377,40 @@ impl<L: Lexer, S: AsRef<str>> Parser<L, S> {
         (first.span, db)
     }
 
+    fn digit(&self, span: Span, radix: u32) -> HexSize {
+        let s = self.slice(span);
+        let s = match radix {
+            10 => s,
+            _ => &s[2..],
+        };
+        HexSize::from_str_radix(&s.replace('_', ""), radix).expect("invalid integer")
+    }
+
+    fn char_literal(&self, span: Span) -> char {
+        let s = self.slice(span);
+        let Some(inner) = s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) else {
+            panic!("unterminated character literal: {s}")
+        };
+        let mut chars = inner.chars();
+        let ch = match chars.next() {
+            Some('\\') => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some(c @ ('\\' | '\'' | '"')) => c,
+                Some(c) => panic!("unknown character escape: \\{c}"),
+                None => panic!("unterminated character literal: {s}"),
+            },
+            Some(c) => c,
+            None => panic!("empty character literal"),
+        };
+        if chars.next().is_some() {
+            panic!("invalid character literal: {s}")
+        }
+        ch
+    }
+
     fn unexpected(&mut self, ad: Advance) -> ! {
         self.mkill_line(ad);
         panic!("unexpected lexeme: {ad:?}")
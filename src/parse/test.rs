@@ -1,7 +1,7 @@
 use expect_test::{expect, Expect};
 
 fn check(s: &str, e: Expect) {
-    let h_ac = super::Parser::new(s).parse();
+    let h_ac = super::Parser::new(s).program();
     let mut label = h_ac
         .labels
         .iter()
@@ -74,7 +74,7 @@ fn pe2() {
             Je(Address(Ident(SymbolU32 { value: 2 })))
             Cmp(Address(Register(Ax, false)), Hex(4000000))
             Jl(Address(Ident(SymbolU32 { value: 1 })))
-            Jmp(Hex(100))
+            Hlt
             Add(Register(Dx, false), Address(Register(Ax, false)))
             Jmp(Address(Ident(SymbolU32 { value: 3 })))"#]],
     );
@@ -160,3 +160,163 @@ fn pe4() {
             Jmp(Address(Ident(SymbolU32 { value: 4 })))"#]],
     );
 }
+
+#[test]
+fn char_literals() {
+    check(
+        r"
+            mov ax, 'a'
+            sub ax, '0'
+            cmp ax, '\n'
+            push '\''
+        ",
+        expect![[r#"
+            []
+            Mov(Register(Ax, false), Hex(97))
+            Sub(Register(Ax, false), Hex(48))
+            Cmp(Address(Register(Ax, false)), Hex(10))
+            Push(Hex(39))"#]],
+    );
+}
+
+#[test]
+fn signed_literals() {
+    check(
+        "
+            mov ax, -5
+            mov bx, +0x10
+            dec bx
+            jne -1
+            jmp +2
+            push -0b11
+        ",
+        expect![[r#"
+            []
+            Mov(Register(Ax, false), IHex(-5))
+            Mov(Register(Bx, false), IHex(16))
+            Dec(Register(Bx, false))
+            Jne(IHex(-1))
+            Jmp(IHex(2))
+            Push(IHex(-3))"#]],
+    );
+}
+
+#[test]
+#[should_panic = "relative jump out of range: -3 from instruction 1"]
+fn relative_jump_out_of_range() {
+    super::Parser::new("mov ax, 1\njmp -3").parse();
+}
+
+#[test]
+fn statics() {
+    use crate::{Register::*, Sequence::*};
+
+    let mut vm = super::Parser::new(
+        r#"
+            lea si, cx, "hello, world"
+            lea di, dx, sparse "hi"
+            str "hello, world"
+        "#,
+    )
+    .parse();
+    assert_eq!(
+        vm.seq,
+        [
+            Lea(Si.into(), Cx.into(), 0),
+            Lea(Di.into(), Dx.into(), 1),
+            Str(0)
+        ]
+    );
+    assert_eq!(vm.static_end(), 4);
+    assert_eq!(
+        &vm.mem[..4],
+        &[0x68656c6c6f2c2077, 0x6f726c6400000000, 104, 105]
+    );
+    vm.run();
+    let top = vm.mem.len() - 2;
+    assert_eq!(&vm.mem[top..], &[12, 0]);
+}
+
+#[test]
+fn memory_size() {
+    let mut small = super::Parser::new("push 1\npush 2\npop ax").parse_with_memory(16);
+    small.run();
+    assert_eq!((small.reg.ax, small.reg.sp, small.mem.len()), (2, 15, 16));
+    assert_eq!(small.mem[15], 1);
+
+    let mut big = super::Parser::new("push 7").parse_with_memory(0x20000);
+    big.run();
+    assert_eq!((big.reg.sp, big.mem[0x1ffff]), (0x1ffff, 7));
+}
+
+#[test]
+fn entry() {
+    let mut vm = super::Parser::new(
+        "
+        .entry main
+        double:
+            add ax, ax
+            ret
+        main:
+            mov ax, 21
+            call double
+        ",
+    )
+    .parse();
+    assert_eq!((vm.entry, vm.reg.ip), (2, 2));
+    assert!(crate::verify::verify(&vm).is_empty());
+    assert!(crate::cfg::Cfg::new(&vm).unreachable.is_empty());
+    vm.run();
+    assert_eq!(vm.reg.ax, 42);
+
+    let vm = super::Parser::new("f:\nret\nstart:\ncall f").parse();
+    assert_eq!((vm.entry, vm.reg.ip), (1, 1));
+}
+
+#[test]
+#[should_panic = "undefined entry label: nowhere"]
+fn undefined_entry() {
+    super::Parser::new(".entry nowhere\nstart:").parse();
+}
+
+#[test]
+fn exit_status() {
+    use crate::watch::Stop;
+
+    let mut vm = super::Parser::new("mov ax, 3\nexit ax\nmov ax, 9").parse();
+    vm.record(8);
+    assert_eq!(vm.run(), Stop::Exit(3));
+    assert_eq!((vm.reg.ax, vm.reg.ip, vm.exit), (3, 3, Some(3)));
+    assert!(vm.step_back());
+    assert_eq!((vm.reg.ip, vm.exit), (1, None));
+
+    let mut vm = super::Parser::new("hlt\nmov ax, 9").parse();
+    crate::bytecode::run(&mut vm).unwrap();
+    assert_eq!((vm.reg.ax, vm.exit), (0, Some(0)));
+    assert_eq!(vm.run(), Stop::Exit(0));
+}
+
+#[test]
+fn written_label() {
+    use crate::{Address, Sequence, Value};
+
+    let src = "
+        mov ax, 0
+        jmp target
+    target:
+        inc ax
+        inc ax
+        inc bx
+    ";
+    // the assembler has no syntax for writing a label, but the vm allows it
+    let mut program = super::Parser::new(src).program();
+    let target = program.si.get("target").unwrap();
+    program.seq[0] = Sequence::Mov(Address::Ident(target), Value::Hex(4));
+    let mut vm = program.build();
+    assert_eq!(
+        vm.seq[1],
+        Sequence::Jmp(Value::Address(Address::Ident(target)))
+    );
+    vm.run();
+    assert_eq!((vm.reg.ax, vm.reg.bx, vm.labels[&target]), (0, 1, 4));
+}
@@ -0,0 +1,89 @@
+//! counts how often each instruction runs
+
+use std::fmt::Write;
+
+use crate::{trace::Tracer, FlagSet, HexSize, HexVm, RegisterSet, Sequence};
+
+#[cfg(test)]
+mod test;
+
+/// a tracer counting executions per instruction index
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Profiler {
+    pub counts: Vec<u64>,
+}
+
+impl Profiler {
+    pub fn new(len: usize) -> Self {
+        Self {
+            counts: vec![0; len],
+        }
+    }
+
+    /// totals per label region, from each label up to the next one
+    pub fn regions<T: Tracer>(&self, vm: &HexVm<T>) -> Vec<(String, u64)> {
+        let mut labels = vm
+            .labels
+            .iter()
+            .map(|(&sym, &i)| (i as usize, vm.si.resolve(sym).unwrap_or("?")))
+            .collect::<Vec<_>>();
+        labels.sort();
+        if labels.first().is_none_or(|&(i, _)| i > 0) {
+            labels.insert(0, (0, "<entry>"));
+        }
+        let mut regions = Vec::<(usize, String)>::new();
+        for (i, name) in labels {
+            match regions.last_mut() {
+                // labels sharing an index share one region
+                Some((at, names)) if *at == i => *names = format!("{names}/{name}"),
+                _ => regions.push((i, name.to_owned())),
+            }
+        }
+        let mut regions = (0..regions.len())
+            .filter(|&n| regions[n].0 < self.counts.len())
+            .map(|n| {
+                let end = regions.get(n + 1).map_or(self.counts.len(), |r| r.0);
+                let total: u64 = self.counts[regions[n].0..end.min(self.counts.len())]
+                    .iter()
+                    .sum();
+                (regions[n].1.clone(), total)
+            })
+            .collect::<Vec<_>>();
+        regions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        regions
+    }
+
+    /// every instruction that ran, hottest first, next to its source line,
+    /// followed by the totals per label region
+    pub fn report<T: Tracer>(&self, vm: &HexVm<T>, src: &str) -> String {
+        let lines = src.lines().collect::<Vec<_>>();
+        let mut hot = (0..self.counts.len())
+            .filter(|&i| self.counts[i] > 0)
+            .collect::<Vec<_>>();
+        hot.sort_by(|&a, &b| self.counts[b].cmp(&self.counts[a]).then(a.cmp(&b)));
+        let mut out = format!("{:>12} {:>6} {:>6}  source\n", "count", "index", "line");
+        for i in hot {
+            let line = vm.lines.get(i).copied();
+            let text = line
+                .and_then(|l| lines.get(l as usize))
+                .map_or("", |l| l.trim());
+            let line = line.map_or_else(|| "?".to_owned(), |l| (l + 1).to_string());
+            let _ = writeln!(out, "{:>12} {i:>6} {line:>6}  {text}", self.counts[i]);
+        }
+        let _ = writeln!(out, "\n{:>12}  label", "total");
+        for (name, total) in self.regions(vm) {
+            let _ = writeln!(out, "{total:>12}  {name}");
+        }
+        out
+    }
+}
+
+impl Tracer for Profiler {
+    fn before(&mut self, ip: HexSize, _: &Sequence, _: &RegisterSet, _: &FlagSet) {
+        let ip = ip as usize;
+        if ip >= self.counts.len() {
+            self.counts.resize(ip + 1, 0);
+        }
+        self.counts[ip] += 1;
+    }
+}
@@ -0,0 +1,40 @@
+use expect_test::expect;
+
+use super::Profiler;
+use crate::parse::Parser;
+
+#[test]
+fn report() {
+    let src = "
+    start:
+        mov cx, 3
+    loop:
+        dec cx
+        cmp cx, 0
+        jne loop
+    end:
+    ";
+    let vm = Parser::new(src).parse();
+    let len = vm.seq.len();
+    let mut vm = vm.with_tracer(Profiler::new(len));
+    vm.run();
+    // expect trims the shared indentation, not the padding
+    let out = vm
+        .tracer
+        .report(&vm, src)
+        .lines()
+        .map(|l| l.trim_start().to_owned() + "\n")
+        .collect::<String>();
+    expect![[r#"
+        count  index   line  source
+        3      1      5  dec cx
+        3      2      6  cmp cx, 0
+        3      3      7  jne loop
+        1      0      3  mov cx, 3
+
+        total  label
+        9  loop
+        1  start
+    "#]]
+    .assert_eq(&out);
+}
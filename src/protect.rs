@@ -0,0 +1,245 @@
+//! segments of memory with permissions, checked while running
+//!
+//! once [`HexVm::protect`] is called, every access must fall in a segment
+//! that allows it, pushes and pops must stay in the stack segment, and
+//! jumps must land inside the program. a violation stops [`HexVm::run`]
+//! with a [`Fault`], leaving the registers and flags as they were before
+//! the faulting instruction.
+
+use std::{cell::Cell, fmt};
+
+use string_interner::DefaultSymbol;
+
+use crate::{trace::Tracer, FlagSet, HexSize, HexVm, RegisterSet};
+
+#[cfg(test)]
+mod test;
+
+/// the stack size the cli protects with
+pub const DEFAULT_STACK: HexSize = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// the read-only strings and vector table
+    Static,
+    Heap,
+    Stack,
+    /// instruction indices rather than memory, only ever executed
+    Code,
+}
+
+/// what an access needs permission for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Perm {
+    Read,
+    Write,
+    /// running an instruction, only ever checked against the code
+    Execute,
+}
+
+/// the permissions of a memory segment, memory is never executed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Perms {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Perms {
+    pub const R: Self = Self {
+        read: true,
+        write: false,
+    };
+    pub const RW: Self = Self {
+        read: true,
+        write: true,
+    };
+
+    fn allows(&self, perm: Perm) -> bool {
+        match perm {
+            Perm::Read => self.read,
+            Perm::Write => self.write,
+            Perm::Execute => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub start: HexSize,
+    pub end: HexSize,
+    pub perms: Perms,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    /// the faulting instruction
+    pub index: usize,
+    /// its zero based source line, if it was parsed
+    pub line: Option<u32>,
+    pub address: HexSize,
+    /// the segment the address is in, if any
+    pub segment: Option<SegmentKind>,
+    pub access: Perm,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Protection {
+    /// memory segments, code is checked against the program length
+    pub segments: Vec<Segment>,
+    /// the first fault of the current step, reads happen through `&HexVm`
+    pending: Cell<Option<(HexSize, Option<SegmentKind>, Perm)>>,
+    /// the current step's memory writes as `(address, old, new)`, undone
+    /// if it faults and only passed on to the tracer if it does not
+    writes: Vec<(HexSize, HexSize, HexSize)>,
+    /// the current step's label writes, undone if it faults
+    labels: Vec<(DefaultSymbol, HexSize)>,
+}
+
+impl Protection {
+    pub(crate) fn begin(&mut self) {
+        self.pending.set(None);
+        self.writes.clear();
+        self.labels.clear();
+    }
+
+    pub(crate) fn mem_write(&mut self, add: HexSize, old: HexSize, new: HexSize) {
+        self.writes.push((add, old, new));
+    }
+
+    pub(crate) fn label_write(&mut self, sym: DefaultSymbol, old: HexSize) {
+        self.labels.push((sym, old));
+    }
+
+    fn segment(&self, add: HexSize) -> Option<&Segment> {
+        self.segments
+            .iter()
+            .find(|s| (s.start..s.end).contains(&add))
+    }
+
+    /// whether the access is allowed, recording a fault when it is not
+    pub(crate) fn check(&self, add: HexSize, access: Perm) -> bool {
+        let segment = self.segment(add);
+        self.allow(
+            add,
+            segment,
+            segment.is_some_and(|s| s.perms.allows(access)),
+            access,
+        )
+    }
+
+    /// like [`Protection::check`], but the address must be in a stack
+    pub(crate) fn check_stack(&self, add: HexSize, access: Perm) -> bool {
+        let segment = self.segment(add);
+        let ok = segment.is_some_and(|s| s.kind == SegmentKind::Stack && s.perms.allows(access));
+        self.allow(add, segment, ok, access)
+    }
+
+    fn allow(&self, add: HexSize, segment: Option<&Segment>, ok: bool, access: Perm) -> bool {
+        // after a fault nothing else in the step goes through
+        if self.pending.get().is_some() {
+            return false;
+        }
+        if !ok {
+            self.pending
+                .set(Some((add, segment.map(|s| s.kind), access)));
+        }
+        ok
+    }
+}
+
+impl<T: Tracer> HexVm<T> {
+    /// splits memory into the static area, a heap and a stack of
+    /// `stack` words at the top, and starts checking every access
+    pub fn protect(&mut self, stack: HexSize) {
+        let end = self.mem.len() as HexSize;
+        let static_end = self.static_end();
+        if stack > end - static_end {
+            panic!("a stack of {stack} words does not fit")
+        }
+        let segment = |kind, start, end, perms| Segment {
+            kind,
+            start,
+            end,
+            perms,
+        };
+        self.protection = Some(Protection {
+            segments: vec![
+                segment(SegmentKind::Static, 0, static_end, Perms::R),
+                segment(SegmentKind::Heap, static_end, end - stack, Perms::RW),
+                segment(SegmentKind::Stack, end - stack, end, Perms::RW),
+            ],
+            ..Default::default()
+        });
+    }
+
+    /// once a step has run, undoes everything it did if it faulted and
+    /// returns true, or passes its writes on to the tracer
+    pub(crate) fn settle(&mut self, before: (RegisterSet, FlagSet, Option<HexSize>)) -> bool {
+        let len = self.seq.len() as HexSize;
+        let Some(p) = &mut self.protection else {
+            return false;
+        };
+        // running exactly past the end is how a program finishes
+        if p.pending.get().is_none() && self.reg.ip > len {
+            let fault = (self.reg.ip, Some(SegmentKind::Code), Perm::Execute);
+            p.pending.set(Some(fault));
+        }
+        if p.pending.get().is_none() {
+            for &(add, old, new) in &p.writes {
+                self.tracer.mem_write(add, old, new);
+            }
+            return false;
+        }
+        for &(add, old, _) in p.writes.iter().rev() {
+            self.mem[add as usize] = old;
+        }
+        for &(sym, old) in p.labels.iter().rev() {
+            self.labels.insert(sym, old);
+        }
+        (self.reg, self.flg, self.exit) = before;
+        // the step never happened, so neither can it be stepped back
+        if let Some(history) = &mut self.history {
+            history.discard();
+        }
+        true
+    }
+
+    /// the fault of the last step, if any
+    pub(crate) fn take_fault(&mut self, index: HexSize) -> Option<Fault> {
+        let (address, segment, access) = self.protection.as_ref()?.pending.take()?;
+        Some(Fault {
+            index: index as usize,
+            line: self.lines.get(index as usize).copied(),
+            address,
+            segment,
+            access,
+        })
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = match self.access {
+            Perm::Read => "read of",
+            Perm::Write => "write to",
+            Perm::Execute => "jump to",
+        };
+        let segment = match self.segment {
+            Some(SegmentKind::Static) => "in static",
+            Some(SegmentKind::Heap) => "in heap",
+            Some(SegmentKind::Stack) => "in stack",
+            Some(SegmentKind::Code) => "past the code",
+            None => "in unmapped memory",
+        };
+        write!(
+            f,
+            "fault: {access} {} {segment} at instruction {}",
+            self.address, self.index
+        )?;
+        if let Some(line) = self.line {
+            write!(f, " (line {})", line + 1)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use expect_test::expect;
+
+use crate::{parse::Parser, trace::JsonTracer, watch::Stop};
+
+/// the stop and registers after running `src` with a stack of `stack` words
+fn fault(src: &'static str, stack: u64, record: bool) -> String {
+    let mut vm = Parser::new(src).parse();
+    if record {
+        vm.record(16);
+    }
+    vm.protect(stack);
+    let stop = vm.run();
+    let top = vm.mem.len() - 3;
+    format!(
+        "{stop}\nip: {}, sp: {}, cycles: {}, top: {:?}\n",
+        vm.reg.ip,
+        vm.reg.sp,
+        vm.cycles,
+        &vm.mem[top..]
+    )
+}
+
+#[test]
+fn static_write() {
+    let src = r#"
+    lea si, cx, "hi"
+    mov [si], 1
+    "#;
+    expect![[r#"
+        fault: write to 0 in static at instruction 1 (line 3)
+        ip: 1, sp: 61166, cycles: 1, top: [0, 0, 0]
+    "#]]
+    .assert_eq(&fault(src, 16, false));
+}
+
+#[test]
+fn stack_overflow() {
+    let src = "
+    f:
+        inc ax
+        call f
+    ";
+    expect![[r#"
+        fault: write to 61149 in heap at instruction 1 (line 4)
+        ip: 1, sp: 61150, cycles: 33, top: [2, 2, 2]
+    "#]]
+    .assert_eq(&fault(src, 16, false));
+}
+
+#[test]
+fn return_address() {
+    let src = "
+        call f
+        jmp end
+    f:
+        mov [sp], 99
+        ret
+    end:
+    ";
+    expect![[r#"
+        fault: jump to 99 past the code at instruction 3 (line 6)
+        ip: 3, sp: 61165, cycles: 2, top: [0, 0, 99]
+    "#]]
+    .assert_eq(&fault(src, 16, false));
+}
+
+#[test]
+fn unmapped_read() {
+    expect![[r#"
+        fault: read of 16777215 in unmapped memory at instruction 0 (line 1)
+        ip: 0, sp: 61166, cycles: 0, top: [0, 0, 0]
+    "#]]
+    .assert_eq(&fault("mov ax, [0xffffff]", 16, false));
+}
+
+#[test]
+fn partial_push_undone() {
+    // the address fits before the stack runs out, the length does not
+    let src = r#"
+        push 7
+        sparse "abcd"
+    "#;
+    expect![[r#"
+        fault: write to 61163 in heap at instruction 1 (line 3)
+        ip: 1, sp: 61165, cycles: 1, top: [0, 0, 7]
+    "#]]
+    .assert_eq(&fault(src, 2, true));
+    assert_eq!(Stop::End, {
+        let mut vm = Parser::new(src).parse();
+        vm.protect(3);
+        vm.run()
+    });
+}
+
+#[test]
+fn partial_push_undone_unrecorded() {
+    let src = r#"
+        push 7
+        sparse "abcd"
+    "#;
+    let mut vm = Parser::new(src)
+        .parse()
+        .with_tracer(JsonTracer::new(Vec::new()));
+    vm.protect(2);
+    assert!(matches!(vm.run(), Stop::Fault(_)));
+    assert_eq!((vm.reg.ip, vm.reg.sp, vm.cycles), (1, 61165, 1));
+    let top = vm.mem.len() - 3;
+    assert_eq!(&vm.mem[top..], &[0, 0, 7]);
+    // the tracer only heard of the write that stayed
+    let trace = String::from_utf8(vm.tracer.finish().unwrap()).unwrap();
+    assert_eq!(trace.matches("\"event\":\"write\"").count(), 1);
+}
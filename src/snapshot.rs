@@ -0,0 +1,216 @@
+//! copies of the full machine state, to fork a run from any point
+//!
+//! a snapshot holds everything that changes while running: registers,
+//! flags, memory, the label table, the cycle count and the exit status.
+//! the sequence and
+//! the static strings are left out, as they are part of the program.
+
+use crate::{trace::Tracer, FlagSet, HexSize, HexVm, RegisterSet};
+
+#[cfg(test)]
+mod test;
+
+const MAGIC: &[u8; 4] = b"HXSN";
+const VERSION: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub reg: RegisterSet,
+    pub flg: FlagSet,
+    pub mem: Box<[HexSize]>,
+    /// every label by name, sorted
+    pub labels: Vec<(String, HexSize)>,
+    pub cycles: u64,
+    /// the status of an `exit` or `hlt` that has run
+    pub exit: Option<HexSize>,
+}
+
+impl<T: Tracer> HexVm<T> {
+    pub fn snapshot(&self) -> Snapshot {
+        let mut labels = self
+            .labels
+            .iter()
+            .map(|(&sym, &at)| (self.si.resolve(sym).unwrap().to_owned(), at))
+            .collect::<Vec<_>>();
+        labels.sort();
+        Snapshot {
+            reg: self.reg,
+            flg: self.flg,
+            mem: self.mem.clone(),
+            labels,
+            cycles: self.cycles,
+            exit: self.exit,
+        }
+    }
+
+    /// puts the vm back into the snapshot's state, the tracer is kept and
+    /// any recorded history is dropped
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        if snapshot.mem.len() != self.mem.len() {
+            panic!(
+                "snapshot has {} words of memory, the vm has {}",
+                snapshot.mem.len(),
+                self.mem.len()
+            )
+        }
+        self.reg = snapshot.reg;
+        self.flg = snapshot.flg;
+        self.mem.copy_from_slice(&snapshot.mem);
+        self.labels.clear();
+        for (name, at) in &snapshot.labels {
+            self.labels.insert(self.si.get_or_intern(name), *at);
+        }
+        self.cycles = snapshot.cycles;
+        self.exit = snapshot.exit;
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+}
+
+impl Snapshot {
+    /// a compact little endian encoding that only stores the runs of
+    /// non-zero memory
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        for word in registers(&self.reg) {
+            out.extend(word.to_le_bytes());
+        }
+        out.push(self.flg.to_word() as u8);
+        out.extend(self.cycles.to_le_bytes());
+        out.push(self.exit.is_some() as u8);
+        out.extend(self.exit.unwrap_or(0).to_le_bytes());
+        out.extend((self.labels.len() as u64).to_le_bytes());
+        for (name, at) in &self.labels {
+            out.extend((name.len() as u64).to_le_bytes());
+            out.extend(name.as_bytes());
+            out.extend(at.to_le_bytes());
+        }
+        let runs = runs(&self.mem);
+        out.extend((self.mem.len() as u64).to_le_bytes());
+        out.extend((runs.len() as u64).to_le_bytes());
+        for (start, end) in runs {
+            out.extend((start as u64).to_le_bytes());
+            out.extend(((end - start) as u64).to_le_bytes());
+            for word in &self.mem[start..end] {
+                out.extend(word.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    /// reads back what [`Snapshot::to_bytes`] wrote
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut r = Reader { bytes };
+        if r.take(4) != MAGIC {
+            panic!("not a snapshot")
+        }
+        let version = r.take(1)[0];
+        if version != VERSION {
+            panic!("unsupported snapshot version: {version}")
+        }
+        let mut reg = [0; 9];
+        reg.iter_mut().for_each(|w| *w = r.word());
+        let [ax, bx, cx, dx, si, di, sp, bp, ip] = reg;
+        let flg = FlagSet::from_word(r.take(1)[0] as HexSize);
+        let cycles = r.word();
+        let exit = match (r.take(1)[0], r.word()) {
+            (0, _) => None,
+            (_, status) => Some(status),
+        };
+        let labels = (0..r.word())
+            .map(|_| {
+                let len = r.word() as usize;
+                let name = std::str::from_utf8(r.take(len))
+                    .expect("invalid snapshot: label is not utf-8")
+                    .to_owned();
+                (name, r.word())
+            })
+            .collect();
+        let mut mem = vec![0; r.word() as usize].into_boxed_slice();
+        for _ in 0..r.word() {
+            let start = r.word() as usize;
+            let len = r.word() as usize;
+            let run = mem
+                .get_mut(start..start + len)
+                .expect("invalid snapshot: memory run out of bounds");
+            run.iter_mut().for_each(|w| *w = r.word());
+        }
+        if !r.bytes.is_empty() {
+            panic!("invalid snapshot: {} trailing bytes", r.bytes.len())
+        }
+        Self {
+            reg: RegisterSet {
+                ax,
+                bx,
+                cx,
+                dx,
+                si,
+                di,
+                sp,
+                bp,
+                ip,
+            },
+            flg,
+            mem,
+            labels,
+            cycles,
+            exit,
+        }
+    }
+}
+
+fn registers(reg: &RegisterSet) -> [HexSize; 9] {
+    let RegisterSet {
+        ax,
+        bx,
+        cx,
+        dx,
+        si,
+        di,
+        sp,
+        bp,
+        ip,
+    } = *reg;
+    [ax, bx, cx, dx, si, di, sp, bp, ip]
+}
+
+/// the `start..end` ranges of non-zero words
+fn runs(mem: &[HexSize]) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, &word) in mem.iter().enumerate() {
+        match (start, word) {
+            (None, 0) | (Some(_), 1..) => (),
+            (None, _) => start = Some(i),
+            (Some(s), 0) => {
+                runs.push((s, i));
+                start = None;
+            }
+        }
+    }
+    if let Some(s) = start {
+        runs.push((s, mem.len()));
+    }
+    runs
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        if self.bytes.len() < len {
+            panic!("invalid snapshot: unexpected end")
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        head
+    }
+
+    fn word(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+}
@@ -0,0 +1,82 @@
+use expect_test::expect;
+
+use super::Snapshot;
+use crate::{parse::Parser, watch::Stop};
+
+const SRC: &str = "
+    mov cx, 0
+loop:
+    inc cx
+    push cx
+    cmp cx, 5
+    jl loop
+";
+
+#[test]
+fn fork() {
+    let mut vm = Parser::new(SRC).parse();
+    for _ in 0..9 {
+        vm.step();
+    }
+    let mid = vm.snapshot();
+    vm.run();
+    let end = vm.snapshot();
+
+    vm.restore(&mid);
+    assert_eq!(vm.snapshot(), mid);
+    vm.run();
+    assert_eq!(vm.snapshot(), end);
+
+    // what if cx had been further along
+    vm.restore(&mid);
+    vm.reg.cx = 4;
+    vm.run();
+    expect![[r#"
+            cx: 5, cycles: 13, stack: [5, 2, 1]
+        "#]]
+    .assert_eq(&format!(
+        "cx: {}, cycles: {}, stack: {:?}\n",
+        vm.reg.cx,
+        vm.cycles,
+        &vm.mem[vm.reg.sp as usize..]
+    ));
+    assert_eq!(end.labels, [("loop".to_owned(), 1)]);
+}
+
+#[test]
+fn bytes() {
+    let mut vm = Parser::new(SRC).parse();
+    vm.run();
+    let snapshot = vm.snapshot();
+    let bytes = snapshot.to_bytes();
+    // a header, one label, and one run of five stack words
+    expect!["195"].assert_eq(&bytes.len().to_string());
+    assert_eq!(Snapshot::from_bytes(&bytes), snapshot);
+}
+
+#[test]
+#[should_panic = "invalid snapshot: unexpected end"]
+fn truncated() {
+    let bytes = Snapshot {
+        reg: Default::default(),
+        flg: Default::default(),
+        mem: vec![0, 1, 0].into(),
+        labels: Vec::new(),
+        cycles: 0,
+        exit: None,
+    }
+    .to_bytes();
+    Snapshot::from_bytes(&bytes[..bytes.len() - 1]);
+}
+
+#[test]
+fn exited() {
+    let mut vm = Parser::new("mov ax, 1\nexit 3\nmov ax, 2").parse();
+    vm.run();
+    let snapshot = Snapshot::from_bytes(&vm.snapshot().to_bytes());
+    assert_eq!(snapshot.exit, Some(3));
+    let mut vm = Parser::new("mov ax, 1\nexit 3\nmov ax, 2").parse();
+    vm.restore(&snapshot);
+    assert_eq!(vm.run(), Stop::Exit(3));
+    assert_eq!(vm.reg.ax, 1);
+}
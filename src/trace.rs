@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+
+use string_interner::Symbol;
+
+use crate::{json::Json, Address, FlagSet, HexSize, Register, RegisterSet, Sequence, Value};
+
+#[cfg(test)]
+mod test;
+
+/// hooks called by [`HexVm`](crate::HexVm) while it executes
+///
+/// every method defaults to doing nothing, so a tracer only implements the
+/// events it cares about.
+#[allow(unused_variables)]
+pub trait Tracer {
+    /// called before the instruction at `ip` runs
+    fn before(&mut self, ip: HexSize, seq: &Sequence, reg: &RegisterSet, flg: &FlagSet) {}
+    /// called after the instruction at `ip` ran, `reg.ip` is the next one
+    fn after(&mut self, ip: HexSize, seq: &Sequence, reg: &RegisterSet, flg: &FlagSet) {}
+    /// called for every word written to memory, the stack included
+    fn mem_write(&mut self, add: HexSize, old: HexSize, new: HexSize) {}
+    /// called once a call at `from` has jumped to `to`
+    fn call(&mut self, from: HexSize, to: HexSize) {}
+    /// called once a return at `from` has jumped to `to`
+    fn ret(&mut self, from: HexSize, to: HexSize) {}
+}
+
+/// the default tracer, compiles away entirely
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoTracer;
+
+impl Tracer for NoTracer {}
+
+/// writes one json object per event, one event per line
+#[derive(Debug)]
+pub struct JsonTracer<W> {
+    out: W,
+    err: Option<io::Error>,
+}
+
+impl<W: Write> JsonTracer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, err: None }
+    }
+
+    /// returns the writer, or the first error met while tracing
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.err {
+            Some(err) => Err(err),
+            None => self.out.flush().map(|_| self.out),
+        }
+    }
+
+    fn line(&mut self, json: Json) {
+        if self.err.is_none() {
+            if let Err(err) = writeln!(self.out, "{json}") {
+                self.err = Some(err);
+            }
+        }
+    }
+
+    fn step(&mut self, event: &str, ip: HexSize, seq: &Sequence, reg: &RegisterSet, flg: &FlagSet) {
+        let regs = Register::ALL.map(|r| (format!("{r:?}").to_lowercase(), reg.get(r).into()));
+        let FlagSet { sf, cf, zf, of } = *flg;
+        self.line(Json::object([
+            ("event", event.into()),
+            ("ip", ip.into()),
+            (
+                "seq",
+                Json::object([
+                    ("op", seq.mnemonic().into()),
+                    ("args", operands(seq).into()),
+                ]),
+            ),
+            ("reg", Json::Object(regs.into())),
+            (
+                "flg",
+                Json::object([
+                    ("sf", sf.into()),
+                    ("cf", cf.into()),
+                    ("zf", zf.into()),
+                    ("of", of.into()),
+                ]),
+            ),
+        ]));
+    }
+}
+
+/// each operand as an object keyed by its kind
+fn operands(seq: &Sequence) -> Vec<Json> {
+    use Sequence::*;
+    let stat = |i: usize| Json::object([("static", (i as HexSize).into())]);
+    match *seq {
+        Mov(a, v) | Add(a, v) | Sub(a, v) => vec![address(a), value(v)],
+        Cmp(a, b) => vec![value(a), value(b)],
+        Jmp(v) | Je(v) | Jne(v) | Jl(v) | Jle(v) | Jg(v) | Jge(v) | Call(v) => vec![value(v)],
+        Push(v) | Mul(v) | Div(v) | Mod(v) | Int(v) | Exit(v) => vec![value(v)],
+        Pop(a) | Inc(a) | Dec(a) => vec![address(a)],
+        Str(i) | Sparse(i) => vec![stat(i)],
+        Lea(a, b, i) => vec![address(a), address(b), stat(i)],
+        Print(a, len) => vec![address(a), value(Value::Hex(len))],
+        Ret | Iret | Hlt => Vec::new(),
+    }
+}
+
+fn value(value: Value) -> Json {
+    match value {
+        Value::Address(add) => address(add),
+        Value::Hex(n) => Json::object([("hex", n.into())]),
+        Value::IHex(n) => Json::object([("ihex", n.into())]),
+    }
+}
+
+fn address(add: Address) -> Json {
+    match add {
+        Address::Register(r, deref) => Json::object([
+            ("reg", format!("{r:?}").to_lowercase().into()),
+            ("deref", deref.into()),
+        ]),
+        Address::Stack(n) => Json::object([("mem", n.into())]),
+        // only labels that are written stay unlinked
+        Address::Ident(sym) => Json::object([("label", (sym.to_usize() as HexSize).into())]),
+    }
+}
+
+impl<W: Write> Tracer for JsonTracer<W> {
+    fn before(&mut self, ip: HexSize, seq: &Sequence, reg: &RegisterSet, flg: &FlagSet) {
+        self.step("before", ip, seq, reg, flg);
+    }
+
+    fn after(&mut self, ip: HexSize, seq: &Sequence, reg: &RegisterSet, flg: &FlagSet) {
+        self.step("after", ip, seq, reg, flg);
+    }
+
+    fn mem_write(&mut self, add: HexSize, old: HexSize, new: HexSize) {
+        self.line(Json::object([
+            ("event", "write".into()),
+            ("add", add.into()),
+            ("old", old.into()),
+            ("new", new.into()),
+        ]));
+    }
+
+    fn call(&mut self, from: HexSize, to: HexSize) {
+        self.line(Json::object([
+            ("event", "call".into()),
+            ("from", from.into()),
+            ("to", to.into()),
+        ]));
+    }
+
+    fn ret(&mut self, from: HexSize, to: HexSize) {
+        self.line(Json::object([
+            ("event", "ret".into()),
+            ("from", from.into()),
+            ("to", to.into()),
+        ]));
+    }
+}
@@ -0,0 +1,49 @@
+use expect_test::expect;
+
+use super::JsonTracer;
+use crate::{json::Json, parse::Parser};
+
+#[test]
+fn json_lines() {
+    let mut vm = Parser::new("push 7\ncall f\njmp +2\nf:\nret")
+        .parse()
+        .with_tracer(JsonTracer::new(Vec::new()));
+    vm.run();
+    let out = String::from_utf8(vm.tracer.finish().unwrap()).unwrap();
+    let events = out
+        .lines()
+        .map(|l| &l[..l.find(",\"reg\"").unwrap_or(l.len())])
+        .collect::<Vec<_>>()
+        .join("\n");
+    expect![[r#"
+        {"event":"before","ip":0,"seq":{"op":"push","args":[{"hex":7}]}
+        {"event":"write","add":61165,"old":0,"new":7}
+        {"event":"after","ip":0,"seq":{"op":"push","args":[{"hex":7}]}
+        {"event":"before","ip":1,"seq":{"op":"call","args":[{"hex":3}]}
+        {"event":"write","add":61164,"old":0,"new":2}
+        {"event":"call","from":1,"to":3}
+        {"event":"after","ip":1,"seq":{"op":"call","args":[{"hex":3}]}
+        {"event":"before","ip":3,"seq":{"op":"ret","args":[]}
+        {"event":"ret","from":3,"to":2}
+        {"event":"after","ip":3,"seq":{"op":"ret","args":[]}
+        {"event":"before","ip":2,"seq":{"op":"jmp","args":[{"hex":4}]}
+        {"event":"after","ip":2,"seq":{"op":"jmp","args":[{"hex":4}]}"#]]
+    .assert_eq(&events);
+}
+
+#[test]
+fn structured() {
+    let mut vm = Parser::new("mov ax, 0xffffffffffffffff\nmov di, 100\nlea [di], cx, \"a\"")
+        .parse()
+        .with_tracer(JsonTracer::new(Vec::new()));
+    vm.run();
+    let out = String::from_utf8(vm.tracer.finish().unwrap()).unwrap();
+    assert!(out.lines().all(|l| Json::parse(l).is_ok()));
+    let lines = out.lines().filter(|l| l.contains("\"before\""));
+    expect![[r#"
+        {"event":"before","ip":0,"seq":{"op":"mov","args":[{"reg":"ax","deref":false},{"hex":18446744073709551615}]},"reg":{"ax":0,"bx":0,"cx":0,"dx":0,"si":0,"di":0,"sp":61166,"bp":61166,"ip":0},"flg":{"sf":false,"cf":false,"zf":false,"of":false}}
+        {"event":"before","ip":1,"seq":{"op":"mov","args":[{"reg":"di","deref":false},{"hex":100}]},"reg":{"ax":18446744073709551615,"bx":0,"cx":0,"dx":0,"si":0,"di":0,"sp":61166,"bp":61166,"ip":1},"flg":{"sf":false,"cf":false,"zf":false,"of":false}}
+        {"event":"before","ip":2,"seq":{"op":"lea","args":[{"reg":"di","deref":true},{"reg":"cx","deref":false},{"static":0}]},"reg":{"ax":18446744073709551615,"bx":0,"cx":0,"dx":0,"si":0,"di":100,"sp":61166,"bp":61166,"ip":2},"flg":{"sf":false,"cf":false,"zf":false,"of":false}}
+    "#]]
+    .assert_eq(&lines.map(|l| l.to_owned() + "\n").collect::<String>());
+}
@@ -0,0 +1,165 @@
+//! static stack balance checking of every routine
+//!
+//! each routine, the entry and every call target, is walked on its own
+//! while tracking the stack depth relative to its start. calls are assumed
+//! to leave the stack as they found it, since their targets are checked as
+//! routines of their own.
+
+use std::fmt::Write;
+
+use crate::{cfg::resolve, trace::Tracer, Address, HexSize, HexVm, Register, Sequence};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// `ret` reached with items left on or taken off the stack, so it pops a
+    /// data word as the return address
+    UnbalancedRet(i64),
+    /// two paths reach the same instruction with different depths
+    Mismatch(i64, i64),
+    /// more is popped than the routine pushed
+    Underflow,
+    /// `sp` is written directly, so the depth is unknown past this point
+    SpWrite,
+    /// a jump or call whose target is only known at runtime
+    DynamicTarget,
+    /// a called routine runs past the last instruction instead of returning
+    FallsOffEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Issue {
+    /// index of the routine's first instruction
+    pub routine: usize,
+    pub index: usize,
+    pub kind: IssueKind,
+}
+
+pub fn verify<T: Tracer>(vm: &HexVm<T>) -> Vec<Issue> {
+    let mut routines = vec![vm.entry as usize];
+    // vector table handlers return with `iret`
+    for sym in vm.vectors.iter().flatten() {
+        match vm.labels.get(sym) {
+            Some(&t) if !routines.contains(&(t as usize)) => routines.push(t as usize),
+            _ => (),
+        }
+    }
+    for (i, seq) in vm.seq.iter().enumerate() {
+        if let Sequence::Call(value) = *seq {
+            match resolve(vm, i, value) {
+                Some(t) if !routines.contains(&(t as usize)) => routines.push(t as usize),
+                _ => (),
+            }
+        }
+    }
+    let mut issues = Vec::new();
+    for routine in routines {
+        routine_issues(vm, routine, &mut issues);
+    }
+    issues
+}
+
+fn routine_issues<T: Tracer>(vm: &HexVm<T>, routine: usize, issues: &mut Vec<Issue>) {
+    use Sequence::*;
+    let len = vm.seq.len();
+    let mut depth = vec![None; len];
+    let mut work = vec![(routine, 0)];
+    let mut issue = |index, kind| {
+        let issue = Issue {
+            routine,
+            index,
+            kind,
+        };
+        if !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    };
+    while let Some((i, d)) = work.pop() {
+        if i >= len {
+            if routine != vm.entry as usize {
+                issue(i.min(len), IssueKind::FallsOffEnd);
+            }
+            continue;
+        }
+        match depth[i] {
+            Some(seen) if seen != d => {
+                issue(i, IssueKind::Mismatch(seen, d));
+                continue;
+            }
+            Some(_) => continue,
+            None => depth[i] = Some(d),
+        }
+        let seq = vm.seq[i];
+        if writes_sp(&seq) {
+            issue(i, IssueKind::SpWrite);
+            continue;
+        }
+        let next = d + match seq {
+            Push(_) => 1,
+            Pop(_) => -1,
+            Str(_) | Sparse(_) => 2,
+            _ => 0,
+        };
+        if next < 0 {
+            issue(i, IssueKind::Underflow);
+            continue;
+        }
+        if matches!(seq, Hlt | Exit(_)) {
+            continue;
+        }
+        if matches!(seq, Ret | Iret) {
+            if d != 0 {
+                issue(i, IssueKind::UnbalancedRet(d));
+            }
+            continue;
+        }
+        if let Some(value) = seq.target() {
+            match resolve(vm, i, value) {
+                // calls are followed through their return site only
+                Some(_) if matches!(seq, Call(_)) => (),
+                Some(t) => work.push((t as usize, next)),
+                None => issue(i, IssueKind::DynamicTarget),
+            }
+        }
+        if !matches!(seq, Jmp(_)) {
+            work.push((i + 1, next));
+        }
+    }
+}
+
+fn writes_sp(seq: &Sequence) -> bool {
+    use Sequence::*;
+    let sp = Address::Register(Register::Sp, false);
+    match *seq {
+        Mov(a, _) | Pop(a) | Add(a, _) | Sub(a, _) | Inc(a) | Dec(a) => a == sp,
+        Lea(a, b, _) => a == sp || b == sp,
+        _ => false,
+    }
+}
+
+/// describes every issue, one per line
+pub fn report<T: Tracer>(vm: &HexVm<T>, issues: &[Issue]) -> String {
+    let name = |i: usize| {
+        vm.labels
+            .iter()
+            .filter(|&(_, &at)| at == i as HexSize)
+            .filter_map(|(&sym, _)| vm.si.resolve(sym))
+            .min()
+            .map_or_else(|| format!("{i}"), str::to_owned)
+    };
+    let mut out = String::new();
+    for issue in issues {
+        let _ = write!(out, "{} at {}: ", name(issue.routine), issue.index);
+        let _ = match issue.kind {
+            IssueKind::UnbalancedRet(d) => writeln!(out, "ret with a stack depth of {d}"),
+            IssueKind::Mismatch(a, b) => writeln!(out, "paths merge with depths {a} and {b}"),
+            IssueKind::Underflow => writeln!(out, "pops more than was pushed"),
+            IssueKind::SpWrite => writeln!(out, "sp written directly, depth unknown"),
+            IssueKind::DynamicTarget => writeln!(out, "target unknown until runtime"),
+            IssueKind::FallsOffEnd => writeln!(out, "runs past the end without ret"),
+        };
+    }
+    out
+}
@@ -0,0 +1,78 @@
+use expect_test::expect;
+
+use crate::parse::Parser;
+
+fn check(src: &str, e: expect_test::Expect) {
+    let vm = Parser::new(src).parse();
+    e.assert_eq(&super::report(&vm, &super::verify(&vm)));
+}
+
+#[test]
+fn euler() {
+    for src in [
+        include_str!("../../project-euler/problem-1.asm"),
+        include_str!("../../project-euler/problem-2.asm"),
+        include_str!("../../project-euler/problem-3.asm"),
+        include_str!("../../project-euler/problem-4.asm"),
+        include_str!("../../project-euler/problem-5.asm"),
+        include_str!("../../project-euler/problem-6.asm"),
+        include_str!("../../project-euler/problem-7.asm"),
+        include_str!("../../project-euler/problem-8.asm"),
+        include_str!("../../project-euler/problem-9.asm"),
+    ] {
+        check(src, expect![""]);
+    }
+}
+
+#[test]
+fn unbalanced() {
+    check(
+        "
+        start:
+            call extra
+            call merge
+            call under
+            mov sp, 10
+            jmp end
+        extra:
+            push ax
+            ret
+        merge:
+            cmp ax, 0
+            je skip
+            push ax
+        skip:
+            pop ax
+            ret
+        under:
+            pop bx
+            ret
+        end:
+        ",
+        expect![[r#"
+            start at 3: sp written directly, depth unknown
+            extra at 6: ret with a stack depth of 1
+            merge at 10: paths merge with depths 1 and 0
+            under at 12: pops more than was pushed
+        "#]],
+    );
+}
+
+#[test]
+fn strings() {
+    // however long the string, only its address and length are pushed
+    check(
+        r#"
+        start:
+            call f
+            jmp end
+        f:
+            str "longer than a word"
+            pop cx
+            pop si
+            ret
+        end:
+        "#,
+        expect![""],
+    );
+}
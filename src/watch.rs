@@ -0,0 +1,227 @@
+//! breakpoints and watchpoints that stop [`HexVm::run`]
+//!
+//! memory is watched through operands, pushes and pops. registers are
+//! watched through operands, and for writes not made through an operand,
+//! such as `mul` to `ax`, by comparing them around each step.
+
+use std::{cell::Cell, fmt};
+
+use crate::{expr::Expr, protect::Fault, trace::Tracer, HexSize, HexVm, Register};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Mem(HexSize),
+    Reg(Register),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    /// any write, even of the same value
+    Write,
+    /// a write of a different value
+    Change,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    pub target: Target,
+    pub access: Access,
+}
+
+/// a watch that triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub watch: Watch,
+    /// the instruction that made the access
+    pub index: usize,
+    /// its zero based source line, if it was parsed
+    pub line: Option<u32>,
+    /// equal to `new` for reads
+    pub old: HexSize,
+    pub new: HexSize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// the instruction index to stop before
+    pub index: HexSize,
+    /// stops only when this is non-zero
+    pub cond: Option<Expr>,
+    /// times the instruction was reached
+    pub hits: u64,
+}
+
+impl Breakpoint {
+    pub fn new(index: HexSize) -> Self {
+        Self {
+            index,
+            cond: None,
+            hits: 0,
+        }
+    }
+
+    pub fn when(index: HexSize, cond: Expr) -> Self {
+        Self {
+            cond: Some(cond),
+            ..Self::new(index)
+        }
+    }
+}
+
+/// why [`HexVm::run`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// ran past the last instruction
+    End,
+    /// about to run the instruction at a breakpoint
+    Break(HexSize),
+    Watch(Hit),
+    /// an access broke a segment's permissions
+    Fault(Fault),
+    /// ran as many instructions as the fuel allows
+    Fuel,
+    /// ran `hlt` or `exit` with the status
+    Exit(HexSize),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Watchpoints {
+    pub breakpoints: Vec<Breakpoint>,
+    pub watches: Vec<Watch>,
+    /// the first access of the current step, reads happen through `&HexVm`
+    pending: Cell<Option<(Watch, HexSize, HexSize)>>,
+    /// the breakpoint last stopped at, passed when running again
+    resume: Option<HexSize>,
+}
+
+impl Watchpoints {
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty() && self.watches.is_empty()
+    }
+
+    pub(crate) fn access(&self, target: Target, access: Access, old: HexSize, new: HexSize) {
+        if self.watches.is_empty() || self.pending.get().is_some() {
+            return;
+        }
+        let hit = self.watches.iter().find(|w| {
+            w.target == target
+                && match w.access {
+                    Access::Read => access == Access::Read,
+                    Access::Write => access != Access::Read,
+                    Access::Change => access != Access::Read && old != new,
+                }
+        });
+        if let Some(&watch) = hit {
+            self.pending.set(Some((watch, old, new)));
+        }
+    }
+}
+
+impl<T: Tracer> HexVm<T> {
+    /// runs until the end, a breakpoint, a watch, a fault or the fuel runs
+    /// out. running again after stopping at a breakpoint resumes past it
+    pub fn run(&mut self) -> Stop {
+        if self.watch.is_empty() && self.protection.is_none() && self.fuel.is_none() {
+            while self.seq.len() > self.reg.ip as usize {
+                self.step();
+            }
+            return self.exit.map_or(Stop::End, Stop::Exit);
+        }
+        self.watch.pending.take();
+        let mut resume = self.watch.resume.take();
+        while self.seq.len() > self.reg.ip as usize {
+            let ip = self.reg.ip;
+            if self.fuel.is_some_and(|fuel| self.cycles >= fuel) {
+                self.watch.resume = resume;
+                return Stop::Fuel;
+            }
+            if resume.take() != Some(ip) && self.breaks(ip) {
+                self.watch.resume = Some(ip);
+                return Stop::Break(ip);
+            }
+            let reg = self.reg;
+            self.step();
+            // a faulting step has already been undone
+            if let Some(fault) = self.take_fault(ip) {
+                self.watch.pending.take();
+                return Stop::Fault(fault);
+            }
+            for r in Register::ALL {
+                let (old, new) = (reg.get(r), self.reg.get(r));
+                if old != new {
+                    self.watch.access(Target::Reg(r), Access::Write, old, new);
+                }
+            }
+            if let Some((watch, old, new)) = self.watch.pending.take() {
+                return Stop::Watch(Hit {
+                    watch,
+                    index: ip as usize,
+                    line: self.lines.get(ip as usize).copied(),
+                    old,
+                    new,
+                });
+            }
+        }
+        self.exit.map_or(Stop::End, Stop::Exit)
+    }
+
+    /// counts a hit on every breakpoint at `ip`, returning whether one of
+    /// them stops
+    fn breaks(&mut self, ip: HexSize) -> bool {
+        let mut breakpoints = std::mem::take(&mut self.watch.breakpoints);
+        let mut stop = false;
+        for b in breakpoints.iter_mut().filter(|b| b.index == ip) {
+            b.hits += 1;
+            stop |= b
+                .cond
+                .as_ref()
+                .is_none_or(|cond| cond.eval(self, b.hits).is_some_and(|v| v != 0));
+        }
+        self.watch.breakpoints = breakpoints;
+        stop
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Mem(add) => write!(f, "[{add}]"),
+            Target::Reg(r) => write!(f, "{}", format!("{r:?}").to_lowercase()),
+        }
+    }
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::End => write!(f, "ended"),
+            Stop::Break(ip) => write!(f, "breakpoint at instruction {ip}"),
+            Stop::Fault(fault) => write!(f, "{fault}"),
+            Stop::Fuel => write!(f, "out of fuel"),
+            Stop::Exit(status) => write!(f, "exited with {status}"),
+            Stop::Watch(hit) => {
+                let access = match hit.watch.access {
+                    Access::Read => "read",
+                    Access::Write => "written",
+                    Access::Change => "changed",
+                };
+                write!(
+                    f,
+                    "{} {access} at instruction {}",
+                    hit.watch.target, hit.index
+                )?;
+                if let Some(line) = hit.line {
+                    write!(f, " (line {})", line + 1)?;
+                }
+                match hit.watch.access {
+                    Access::Read => write!(f, ": {}", hit.new),
+                    _ => write!(f, ": {} -> {}", hit.old, hit.new),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use expect_test::expect;
+
+use super::{Access, Breakpoint, Stop, Target, Watch};
+use crate::{parse::Parser, Register, HEX_MEM_SIZE};
+
+const SRC: &str = "
+    mov ax, 2
+    call f
+    mul ax
+    jmp end
+f:
+    add ax, 3
+    mov [sp], 99
+    ret
+end:
+";
+
+fn stops(watches: Vec<Watch>, breakpoints: Vec<Breakpoint>) -> String {
+    let mut vm = Parser::new(SRC).parse();
+    vm.watch.watches = watches;
+    vm.watch.breakpoints = breakpoints;
+    let mut out = String::new();
+    for _ in 0..8 {
+        let stop = vm.run();
+        out += &format!("{stop}\n");
+        if stop == Stop::End {
+            break;
+        }
+    }
+    out
+}
+
+#[test]
+fn return_address() {
+    let watch = Watch {
+        target: Target::Mem(HEX_MEM_SIZE - 1),
+        access: Access::Change,
+    };
+    // catches `mov [sp], 99` before `ret` jumps to it
+    expect![[r#"
+        [61165] changed at instruction 1 (line 3): 0 -> 2
+        [61165] changed at instruction 5 (line 8): 2 -> 99
+        ended
+    "#]]
+    .assert_eq(&stops(vec![watch], Vec::new()));
+}
+
+#[test]
+fn registers() {
+    let ax = |access| Watch {
+        target: Target::Reg(Register::Ax),
+        access,
+    };
+    expect![[r#"
+        ax written at instruction 0 (line 2): 0 -> 2
+        ax written at instruction 4 (line 7): 2 -> 5
+        ended
+    "#]]
+    .assert_eq(&stops(vec![ax(Access::Write)], Vec::new()));
+    expect![[r#"
+        ax read at instruction 4 (line 7): 2
+        breakpoint at instruction 5
+        ended
+    "#]]
+    .assert_eq(&stops(vec![ax(Access::Read)], vec![Breakpoint::new(5)]));
+}